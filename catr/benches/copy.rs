@@ -0,0 +1,50 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const PRG: &str = env!("CARGO_BIN_EXE_catr");
+const INPUT_LINES: usize = 500_000;
+
+// --------------------------------------------------
+fn gen_input(path: &Path) -> u64 {
+    let mut file = BufWriter::new(File::create(path).unwrap());
+    for i in 0..INPUT_LINES {
+        writeln!(file, "{:>8} The quick brown fox jumps over the lazy dog.", i).unwrap();
+    }
+    file.flush().unwrap();
+    path.metadata().unwrap().len()
+}
+
+// --------------------------------------------------
+fn cat(args: &[&str], input: &Path, output: &Path) {
+    let status = Command::new(PRG)
+        .args(args)
+        .arg(input)
+        .stdout(Stdio::from(File::create(output).unwrap()))
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+// --------------------------------------------------
+// Compare the plain copy path with the per-line path. A squeeze (-s) of
+// a file without blank lines goes through read_until but writes the
+// same bytes, so both runs produce identical output.
+fn copy_vs_lines(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input.txt");
+    let output = dir.path().join("output.txt");
+    let size = gen_input(&input);
+
+    let mut group = c.benchmark_group("catr");
+    group.throughput(Throughput::Bytes(size));
+    group.sample_size(20);
+    group.bench_function("copy", |b| b.iter(|| cat(&[], &input, &output)));
+    group.bench_function("lines", |b| b.iter(|| cat(&["-s"], &input, &output)));
+    group.finish();
+}
+
+criterion_group!(benches, copy_vs_lines);
+criterion_main!(benches);
@@ -0,0 +1,172 @@
+use std::io::{self, BufRead};
+
+// Compressed formats we recognise by their leading magic bytes. Each one
+// is only compiled in when its cargo feature is enabled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "bzip2")]
+    Bzip2,
+    #[cfg(feature = "xz")]
+    Xz,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+#[cfg(feature = "gzip")]
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+#[cfg(feature = "bzip2")]
+const BZIP2_BLOCK_MAGIC: &[u8] = &[0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+#[cfg(feature = "bzip2")]
+const BZIP2_EOS_MAGIC: &[u8] = &[0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+#[cfg(feature = "xz")]
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+#[cfg(feature = "zstd")]
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+// The most bytes sniff() looks at: "BZh", the block size and a block magic.
+const SNIFF_LEN: usize = 10;
+
+// Look at the start of a stream and report which enabled format it is in,
+// if any. Formats whose feature is disabled pass through as plain bytes.
+#[allow(unused_variables)]
+pub fn sniff(head: &[u8]) -> Option<Format> {
+    #[cfg(feature = "gzip")]
+    if head.starts_with(GZIP_MAGIC) {
+        return Some(Format::Gzip);
+    }
+    // "BZh" alone turns up in plain text, so the block size digit and the
+    // magic of the first block (or of the end of an empty stream) must
+    // follow.
+    #[cfg(feature = "bzip2")]
+    if let [b'B', b'Z', b'h', b'1'..=b'9', rest @ ..] = head {
+        if rest.starts_with(BZIP2_BLOCK_MAGIC) || rest.starts_with(BZIP2_EOS_MAGIC) {
+            return Some(Format::Bzip2);
+        }
+    }
+    #[cfg(feature = "xz")]
+    if head.starts_with(XZ_MAGIC) {
+        return Some(Format::Xz);
+    }
+    #[cfg(feature = "zstd")]
+    if head.starts_with(ZSTD_MAGIC) {
+        return Some(Format::Zstd);
+    }
+    None
+}
+
+// Whether `head` is too short to tell, but could still be the start of an
+// enabled format, so more bytes are worth waiting for.
+#[allow(unused_variables, unused_mut)]
+fn partial_magic(head: &[u8]) -> bool {
+    let prefix_of = |magic: &[u8]| head.len() < magic.len() && magic.starts_with(head);
+    let mut partial = false;
+    #[cfg(feature = "gzip")]
+    {
+        partial |= prefix_of(GZIP_MAGIC);
+    }
+    #[cfg(feature = "bzip2")]
+    {
+        let size = match head.get(3) {
+            Some(&size) if (b'1'..=b'9').contains(&size) => size,
+            _ => b'9',
+        };
+        for block in [BZIP2_BLOCK_MAGIC, BZIP2_EOS_MAGIC] {
+            partial |= prefix_of(&[b"BZh", &[size][..], block].concat());
+        }
+    }
+    #[cfg(feature = "xz")]
+    {
+        partial |= prefix_of(XZ_MAGIC);
+    }
+    #[cfg(feature = "zstd")]
+    {
+        partial |= prefix_of(ZSTD_MAGIC);
+    }
+    partial
+}
+
+// Sniff a stream whose first bytes may arrive a few at a time, as from a
+// pipe. While they could still be the start of a magic number, bytes are
+// taken off the reader until there are enough to tell; those are returned
+// too and come before whatever is left in the reader. Anything else is
+// decided on the first read, so text typed at a terminal isn't held up.
+pub fn sniff_reader(reader: &mut impl BufRead) -> io::Result<(Option<Format>, Vec<u8>)> {
+    let buf = reader.fill_buf()?;
+    if buf.is_empty() || !partial_magic(buf) {
+        return Ok((sniff(buf), Vec::new()));
+    }
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    while head.len() < SNIFF_LEN && partial_magic(&head) {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break; // eof
+        }
+        let n = buf.len().min(SNIFF_LEN - head.len());
+        head.extend_from_slice(&buf[..n]);
+        reader.consume(n);
+    }
+    Ok((sniff(&head), head))
+}
+
+// Wrap a reader in a streaming decoder. Concatenated members (as written
+// by `cat a.gz b.gz`) are decoded one after the other, like zcat does.
+#[allow(unused_variables, unreachable_code)]
+pub fn decoder<R: BufRead + 'static>(format: Format, reader: R) -> io::Result<Box<dyn BufRead>> {
+    Ok(match format {
+        #[cfg(feature = "gzip")]
+        Format::Gzip => Box::new(io::BufReader::new(flate2::bufread::MultiGzDecoder::new(
+            reader,
+        ))),
+        #[cfg(feature = "bzip2")]
+        Format::Bzip2 => Box::new(io::BufReader::new(bzip2::bufread::MultiBzDecoder::new(
+            reader,
+        ))),
+        #[cfg(feature = "xz")]
+        Format::Xz => Box::new(io::BufReader::new(
+            xz2::bufread::XzDecoder::new_multi_decoder(reader),
+        )),
+        #[cfg(feature = "zstd")]
+        Format::Zstd => Box::new(io::BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(reader)?,
+        )),
+    })
+}
+
+#[test]
+#[cfg(feature = "bzip2")]
+fn test_sniff_bzip2() {
+    let block = b"BZh91AY&SY\x00";
+    assert_eq!(sniff(block), Some(Format::Bzip2));
+    let empty = b"BZh9\x17\x72\x45\x38\x50\x90\x00\x00\x00\x00";
+    assert_eq!(sniff(empty), Some(Format::Bzip2));
+
+    assert_eq!(sniff(b"BZhello\n"), None);
+    assert_eq!(sniff(b"BZh0" as &[u8]), None);
+    assert_eq!(sniff(b"BZh91AY&S" as &[u8]), None);
+}
+
+#[test]
+#[cfg(feature = "xz")]
+fn test_sniff_reader() {
+    // An xz stream whose magic number arrives over several reads
+    let data = [XZ_MAGIC, b"rest of the stream"].concat();
+    let mut reader = io::BufReader::with_capacity(1, &data[..]);
+    let (format, head) = sniff_reader(&mut reader).unwrap();
+    assert_eq!(format, Some(Format::Xz));
+    assert_eq!(head, XZ_MAGIC);
+
+    // Text is decided on the first read, leaving the reader alone
+    let mut reader = io::BufReader::with_capacity(1, &b"hi\n"[..]);
+    let (format, head) = sniff_reader(&mut reader).unwrap();
+    assert_eq!(format, None);
+    assert!(head.is_empty());
+    assert_eq!(reader.fill_buf().unwrap(), b"h");
+
+    // A stream that ends part way through a magic number is plain
+    let mut reader = io::BufReader::with_capacity(1, &XZ_MAGIC[..3]);
+    let (format, head) = sniff_reader(&mut reader).unwrap();
+    assert_eq!(format, None);
+    assert_eq!(head, &XZ_MAGIC[..3]);
+}
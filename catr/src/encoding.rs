@@ -0,0 +1,108 @@
+use crate::MyResult;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use std::io::{self, BufRead, BufReader, Read};
+
+// Input encodings for --from-encoding. Auto only converts input that
+// starts with a byte order mark and passes everything else through.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEncoding {
+    Auto,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Windows1252,
+}
+
+pub fn parse_encoding(val: &str) -> MyResult<InputEncoding> {
+    match val.to_ascii_lowercase().as_str() {
+        "auto" => Ok(InputEncoding::Auto),
+        "utf-16le" | "utf16le" => Ok(InputEncoding::Utf16Le),
+        "utf-16be" | "utf16be" => Ok(InputEncoding::Utf16Be),
+        "latin1" | "latin-1" | "iso-8859-1" => Ok(InputEncoding::Latin1),
+        "windows-1252" | "cp1252" => Ok(InputEncoding::Windows1252),
+        _ => Err(From::from(val)),
+    }
+}
+
+// Wrap a reader so that it yields UTF-8. Except for Latin-1, where every
+// byte is a character, a byte order mark is removed and takes precedence
+// over the requested encoding.
+pub fn decoder<R: Read + 'static>(encoding: InputEncoding, reader: R) -> Box<dyn BufRead> {
+    let label: Option<&'static Encoding> = match encoding {
+        InputEncoding::Auto => None,
+        InputEncoding::Utf16Le => Some(UTF_16LE),
+        InputEncoding::Utf16Be => Some(UTF_16BE),
+        InputEncoding::Windows1252 => Some(WINDOWS_1252),
+        // encoding_rs maps Latin-1 to Windows-1252, which turns the C1
+        // control bytes into printable characters, so decode it here.
+        InputEncoding::Latin1 => return Box::new(BufReader::new(Latin1Decoder::new(reader))),
+    };
+    let decoder = DecodeReaderBytesBuilder::new()
+        .encoding(label)
+        .bom_override(true)
+        .strip_bom(true)
+        .utf8_passthru(true)
+        .build(reader);
+    Box::new(BufReader::new(decoder))
+}
+
+// ISO-8859-1 maps every byte to the code point with the same value.
+struct Latin1Decoder<R> {
+    inner: R,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> Latin1Decoder<R> {
+    fn new(inner: R) -> Self {
+        Latin1Decoder {
+            inner,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for Latin1Decoder<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() {
+            let mut raw = [0; 8192];
+            let n = self.inner.read(&mut raw)?;
+            self.buf.clear();
+            self.pos = 0;
+            for &byte in &raw[..n] {
+                let mut utf8 = [0; 2];
+                self.buf
+                    .extend_from_slice(char::from(byte).encode_utf8(&mut utf8).as_bytes());
+            }
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_parse_encoding() {
+    let res = parse_encoding("UTF-16LE");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), InputEncoding::Utf16Le);
+
+    let res = parse_encoding("iso-8859-1");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), InputEncoding::Latin1);
+
+    let res = parse_encoding("ebcdic");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "ebcdic");
+}
+
+#[test]
+fn test_latin1_decoder() {
+    let mut out = String::new();
+    let res = Latin1Decoder::new(&b"caf\xe9 \x80"[..]).read_to_string(&mut out);
+    assert!(res.is_ok());
+    assert_eq!(out, "caf\u{e9} \u{80}");
+}
@@ -0,0 +1,149 @@
+use crate::{cat_lines, decompress, file_id, formats_lines, Config, LineState, MyResult};
+use notify::{PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+// How often to look at the file when no change notification arrives.
+// This also covers filesystems where notifications never come.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Wakes the follow loop when something in the file's directory changes.
+// Uses inotify on Linux (and the native API elsewhere), falling back to a
+// polling watcher when that can't be created or can't watch the
+// directory. With no watcher at all, the loop just wakes every
+// POLL_INTERVAL; the sender is kept so waiting never returns at once.
+struct ChangeWatcher {
+    rx: Receiver<()>,
+    _tx: Sender<()>,
+    _watcher: Option<Box<dyn Watcher>>,
+}
+
+// A watcher on `dir` that signals `tx` whenever something in it changes.
+fn watch_dir(dir: &Path, tx: Sender<()>, poll: bool) -> notify::Result<Box<dyn Watcher>> {
+    let handler = move |_| {
+        let _ = tx.send(());
+    };
+    let mut watcher: Box<dyn Watcher> = match poll {
+        false => Box::new(RecommendedWatcher::new(handler, notify::Config::default())?),
+        true => {
+            let config = notify::Config::default().with_poll_interval(POLL_INTERVAL);
+            Box::new(PollWatcher::new(handler, config)?)
+        }
+    };
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
+
+impl ChangeWatcher {
+    fn new(path: &Path) -> Self {
+        let (tx, rx) = mpsc::channel();
+        // Watch the directory so a file created under the same name after
+        // rotation is noticed as well.
+        let dir = match path.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _ => Path::new("."),
+        };
+        let watcher = watch_dir(dir, tx.clone(), false)
+            .or_else(|_| watch_dir(dir, tx.clone(), true))
+            .ok();
+        ChangeWatcher {
+            rx,
+            _tx: tx,
+            _watcher: watcher,
+        }
+    }
+
+    // Block until a change is reported or the poll interval has passed.
+    fn wait(&self) {
+        if self.rx.recv_timeout(POLL_INTERVAL).is_ok() {
+            // Collapse a burst of events into a single wakeup.
+            while self.rx.try_recv().is_ok() {}
+        }
+    }
+}
+
+// Write everything that is currently readable, keeping the line state so
+// numbering carries on where it stopped.
+fn drain(
+    reader: &mut BufReader<File>,
+    out: &mut impl Write,
+    config: &Config,
+    state: &mut LineState,
+) -> MyResult<()> {
+    if formats_lines(config) {
+        cat_lines(&mut *reader, out, config, state)?;
+    } else {
+        io::copy(reader, out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// Print a file and then keep printing data appended to it, like tail -f.
+// A truncated file is read again from the start, and when the name starts
+// pointing at a new file (log rotation) the rest of the old file is
+// printed before switching to the new one. This only returns on error.
+pub fn follow(
+    filename: &str,
+    out: &mut impl Write,
+    config: &Config,
+    state: &mut LineState,
+) -> MyResult<()> {
+    let path = Path::new(filename);
+    let watcher = ChangeWatcher::new(path);
+    let file = File::open(path)?;
+    let mut id = file_id(&file.metadata()?);
+    let mut reader = BufReader::new(file);
+    // A decoder can't pick up where it stopped once the file grows, so
+    // compressed input is refused rather than printed as raw bytes.
+    if config.decompress && decompress::sniff(reader.fill_buf()?).is_some() {
+        return Err(From::from("cannot follow compressed input"));
+    }
+    loop {
+        drain(&mut reader, out, config, state)?;
+        watcher.wait();
+        match fs::metadata(path) {
+            Ok(meta) if file_id(&meta) != id => {
+                drain(&mut reader, out, config, state)?;
+                if let Ok(file) = File::open(path) {
+                    id = file_id(&file.metadata()?);
+                    reader = BufReader::new(file);
+                }
+            }
+            Ok(meta) if meta.len() < reader.stream_position()? => {
+                eprintln!("catr: {}: file truncated", filename);
+                reader.seek(SeekFrom::Start(0))?;
+            }
+            // A missing file may be between rotation steps, so keep
+            // waiting for it to come back.
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_change_watcher_fallback() {
+    // inotify can't watch a directory that doesn't exist, so this is left
+    // to the polling watcher.
+    let dir = Path::new("no/such/dir");
+    assert!(watch_dir(dir, mpsc::channel().0, false).is_err());
+    let watcher = ChangeWatcher::new(&dir.join("file.log"));
+    assert!(watcher._watcher.is_some());
+
+    // With no watcher at all, waiting still takes the poll interval
+    // instead of returning at once.
+    let (tx, rx) = mpsc::channel();
+    let watcher = ChangeWatcher {
+        rx,
+        _tx: tx,
+        _watcher: None,
+    };
+    let start = std::time::Instant::now();
+    for _ in 0..2 {
+        watcher.wait();
+    }
+    assert!(start.elapsed() >= POLL_INTERVAL * 2);
+}
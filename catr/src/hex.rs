@@ -0,0 +1,302 @@
+use crate::{error_message, output, Config, MyResult};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+const LINE_LEN: usize = 16;
+const PLAIN_LINE_LEN: usize = 30;
+
+// Writes the canonical `hexdump -C` layout. Offsets run on across inputs,
+// and repeated lines are collapsed into a single "*".
+struct CanonicalDump {
+    offset: u64,
+    line: Vec<u8>,
+    prev: Option<Vec<u8>>,
+    squeezing: bool,
+}
+
+impl CanonicalDump {
+    fn new() -> Self {
+        CanonicalDump {
+            offset: 0,
+            line: Vec::with_capacity(LINE_LEN),
+            prev: None,
+            squeezing: false,
+        }
+    }
+
+    fn write(&mut self, data: &[u8], out: &mut impl Write) -> io::Result<()> {
+        for &byte in data {
+            self.line.push(byte);
+            if self.line.len() == LINE_LEN {
+                self.flush_line(out)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_line(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.line.len() == LINE_LEN && self.prev.as_ref() == Some(&self.line) {
+            if !self.squeezing {
+                writeln!(out, "*")?;
+                self.squeezing = true;
+            }
+        } else {
+            self.squeezing = false;
+            write_line(self.offset, &self.line, out)?;
+        }
+        self.offset += self.line.len() as u64;
+        self.prev = Some(std::mem::replace(
+            &mut self.line,
+            Vec::with_capacity(LINE_LEN),
+        ));
+        Ok(())
+    }
+
+    fn finish(mut self, out: &mut impl Write) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.flush_line(out)?;
+        }
+        if self.offset > 0 {
+            writeln!(out, "{:08x}", self.offset)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_line(offset: u64, line: &[u8], out: &mut impl Write) -> io::Result<()> {
+    let mut text = format!("{:08x}  ", offset);
+    for i in 0..LINE_LEN {
+        match line.get(i) {
+            Some(byte) => text.push_str(&format!("{:02x} ", byte)),
+            None => text.push_str("   "),
+        }
+        if i == LINE_LEN / 2 - 1 {
+            text.push(' ');
+        }
+    }
+    text.push_str(" |");
+    text.extend(line.iter().map(|&byte| match byte {
+        0x20..=0x7e => byte as char,
+        _ => '.',
+    }));
+    text.push('|');
+    writeln!(out, "{}", text)
+}
+
+// Writes `xxd -p` output: bare hex, 30 bytes to a line.
+struct PlainDump {
+    column: usize,
+}
+
+impl PlainDump {
+    fn write(&mut self, data: &[u8], out: &mut impl Write) -> io::Result<()> {
+        for byte in data {
+            write!(out, "{:02x}", byte)?;
+            self.column += 1;
+            if self.column == PLAIN_LINE_LEN {
+                writeln!(out)?;
+                self.column = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self, out: &mut impl Write) -> io::Result<()> {
+        if self.column > 0 {
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_hex_byte(token: &str) -> Option<u8> {
+    match token.len() {
+        2 => u8::from_str_radix(token, 16).ok(),
+        _ => None,
+    }
+}
+
+// Turn `xxd -p` style hex back into bytes. Whitespace is ignored.
+fn undump_plain(mut file: impl BufRead, out: &mut impl Write) -> MyResult<()> {
+    let mut pending: Option<char> = None;
+    let mut line = String::new();
+    let mut line_num = 0;
+    while file.read_line(&mut line)? > 0 {
+        line_num += 1;
+        for c in line.chars().filter(|c| !c.is_whitespace()) {
+            if !c.is_ascii_hexdigit() {
+                return Err(format!("line {}: invalid hex digit '{}'", line_num, c).into());
+            }
+            match pending.take() {
+                Some(high) => {
+                    let pair = format!("{}{}", high, c);
+                    out.write_all(&[parse_hex_byte(&pair).unwrap()])?;
+                }
+                None => pending = Some(c),
+            }
+        }
+        line.clear();
+    }
+    match pending {
+        Some(_) => Err(From::from("odd number of hex digits")),
+        None => Ok(()),
+    }
+}
+
+// Turn a `hexdump -C` dump back into bytes, expanding "*" lines by
+// repeating the line before them up to the next offset.
+fn undump_canonical(mut file: impl BufRead, out: &mut impl Write) -> MyResult<()> {
+    let mut written: u64 = 0;
+    let mut prev: Vec<u8> = Vec::new();
+    let mut repeat = false;
+    let mut line = String::new();
+    let mut line_num = 0;
+    while file.read_line(&mut line)? > 0 {
+        line_num += 1;
+        let text = line.trim_end();
+        if text.is_empty() {
+            line.clear();
+            continue;
+        }
+        if text == "*" {
+            repeat = true;
+            line.clear();
+            continue;
+        }
+        let bad_line = || format!("line {}: not a hexdump -C line", line_num);
+        let (offset, rest) = text.split_once(' ').unwrap_or((text, ""));
+        let offset = u64::from_str_radix(offset, 16).map_err(|_| bad_line())?;
+        if repeat {
+            while written < offset && !prev.is_empty() {
+                let n = (offset - written).min(prev.len() as u64) as usize;
+                out.write_all(&prev[..n])?;
+                written += n as u64;
+            }
+            repeat = false;
+        }
+        if offset != written {
+            return Err(format!("line {}: expected offset {:08x}", line_num, written).into());
+        }
+        let hex = rest.split('|').next().unwrap_or("");
+        let bytes = hex
+            .split_whitespace()
+            .map(parse_hex_byte)
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(bad_line)?;
+        out.write_all(&bytes)?;
+        written += bytes.len() as u64;
+        if !bytes.is_empty() {
+            prev = bytes;
+        }
+        line.clear();
+    }
+    Ok(())
+}
+
+// A dump shows the bytes as they are stored, so inputs are read without
+// decompressing or transcoding them.
+fn open_raw(filename: &str) -> MyResult<Box<dyn BufRead>> {
+    match filename {
+        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
+        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+    }
+}
+
+// Dump every input as hex (or, with --reverse, turn dumps back into
+// bytes). Inputs that can't be read are reported and skipped, and a
+// failure writing `out` stops the run with a WriteError. Returns false if
+// any input failed.
+pub fn run(config: &Config, out: &mut impl Write, outputs: &[(u64, u64)]) -> MyResult<bool> {
+    let out = &mut output::Tracked::new(out);
+    let mut canonical = CanonicalDump::new();
+    let mut plain = PlainDump { column: 0 };
+    let mut ok = true;
+    for (file_num, filename) in config.files.iter().enumerate() {
+        if output::is_output(filename, outputs) {
+            eprintln!(
+                "catr: {}: input file is output file",
+                config.input_name(file_num)
+            );
+            ok = false;
+            continue;
+        }
+        let result = open_raw(filename).and_then(|mut file| {
+            if config.hex_reverse && config.hex_plain {
+                return undump_plain(file, &mut *out);
+            } else if config.hex_reverse {
+                return undump_canonical(file, &mut *out);
+            }
+            let mut buf = [0; 8192];
+            loop {
+                let n = file.read(&mut buf)?;
+                if n == 0 {
+                    break Ok(());
+                }
+                if config.hex_plain {
+                    plain.write(&buf[..n], &mut *out)?;
+                } else {
+                    canonical.write(&buf[..n], &mut *out)?;
+                }
+            }
+        });
+        if let Err(err) = result {
+            if out.failed {
+                return Err(output::WriteError(err).into());
+            }
+            eprintln!(
+                "catr: {}: {}",
+                config.input_name(file_num),
+                error_message(&*err)
+            );
+            ok = false;
+        }
+    }
+    let finished = if !config.hex_reverse && config.hex_plain {
+        plain.finish(&mut *out)
+    } else if !config.hex_reverse {
+        canonical.finish(&mut *out)
+    } else {
+        Ok(())
+    };
+    finished
+        .and_then(|_| out.flush())
+        .map_err(|err| output::WriteError(err.into()))?;
+    Ok(ok)
+}
+
+#[test]
+fn test_canonical_dump() {
+    let mut out = Vec::new();
+    let mut dump = CanonicalDump::new();
+    let res = dump
+        .write(&[b'a'; 40], &mut out)
+        .and_then(|_| dump.finish(&mut out));
+    assert!(res.is_ok());
+    assert_eq!(
+        String::from_utf8(out.clone()).unwrap(),
+        "00000000  61 61 61 61 61 61 61 61  61 61 61 61 61 61 61 61  |aaaaaaaaaaaaaaaa|\n\
+         *\n\
+         00000020  61 61 61 61 61 61 61 61                           |aaaaaaaa|\n\
+         00000028\n"
+    );
+
+    let mut bytes = Vec::new();
+    let res = undump_canonical(&out[..], &mut bytes);
+    assert!(res.is_ok());
+    assert_eq!(bytes, vec![b'a'; 40]);
+}
+
+#[test]
+fn test_undump_plain() {
+    let mut bytes = Vec::new();
+    let res = undump_plain(&b"6869\n0a\n"[..], &mut bytes);
+    assert!(res.is_ok());
+    assert_eq!(bytes, b"hi\n");
+
+    let res = undump_plain(&b"68\nzz\n"[..], &mut bytes);
+    assert!(res.is_err());
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "line 2: invalid hex digit 'z'"
+    );
+}
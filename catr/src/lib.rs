@@ -1,19 +1,84 @@
 use std::error::Error;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::fs::{File, Metadata};
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
 use clap::{App, Arg};
+use encoding::InputEncoding;
+use range::LineRange;
+
+mod decompress;
+mod encoding;
+mod follow;
+mod hex;
+mod manifest;
+mod output;
+mod prefetch;
+#[cfg(feature = "pretty")]
+mod pretty;
+mod range;
+mod tee;
+mod walk;
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
+    // "MANIFEST:LINE" for each of `files` that came from --files-from or
+    // --files0-from
+    origins: Vec<Option<String>>,
+    // Bad manifest entries, reported and skipped when the inputs are read
+    manifest_errors: Vec<String>,
     number_lines: bool,
     number_nonblank_lines: bool,
+    show_ends: bool,
+    show_tabs: bool,
+    show_nonprinting: bool,
+    squeeze_blank: bool,
+    number_per_file: bool,
+    number_start: usize,
+    number_width: usize,
+    number_separator: String,
+    decompress: bool,
+    follow: bool,
+    ranges: Option<Vec<LineRange>>,
+    encoding: Option<InputEncoding>,
+    eol: Eol,
+    ensure_final_newline: bool,
+    hex: bool,
+    hex_plain: bool,
+    hex_reverse: bool,
+    prefetch: usize,
+    output: Option<String>,
+    tees: Vec<tee::TeeTarget>,
+    headers: bool,
+    separator: Option<String>,
+    recursive: bool,
+    includes: walk::Globs,
+    excludes: walk::Globs,
+    gitignore: bool,
+    binary: bool,
+    #[cfg(feature = "pretty")]
+    pretty: bool,
 }
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+/// Line endings to write, as chosen with --eol.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Eol {
+    /// Leave line endings as they are in the input.
+    Keep,
+    /// Write LF, turning CRLF endings into LF.
+    Lf,
+    /// Write CRLF, turning LF endings into CRLF.
+    Crlf,
+}
+
+// Read buffer for plain copies the kernel can't do for us.
+const COPY_BUF_SIZE: usize = 128 * 1024;
+
 pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("catr")
+    let app = App::new("catr")
     .version("0.1.0")
     .author("Bill hegazy <bill.hegazy@gmail.com>")
     .about("Rust cat")
@@ -24,6 +89,21 @@ pub fn get_args() -> MyResult<Config> {
         .multiple(true)
         .default_value("-"),
     )
+    .arg(
+        Arg::with_name("files_from")
+        .value_name("F")
+        .help("Also read input file names from F, one per line; - reads them from stdin")
+        .long("files-from")
+        .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("files0_from")
+        .value_name("F")
+        .help("Also read NUL-terminated input file names from F")
+        .long("files0-from")
+        .takes_value(true)
+        .conflicts_with("files_from"),
+    )
     .arg(
         Arg::with_name("number")
             .help("Print line numbers")
@@ -38,20 +118,641 @@ pub fn get_args() -> MyResult<Config> {
         .long("number-nonblank")
         .takes_value(false),
         )
-    .get_matches();
+    .arg(
+        Arg::with_name("number_per_file")
+        .help("Restart line numbers for each file")
+        .long("number-per-file")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("number_start")
+        .value_name("N")
+        .help("First line number")
+        .long("number-start")
+        .default_value("1"),
+    )
+    .arg(
+        Arg::with_name("number_width")
+        .value_name("N")
+        .help("Width of the line number column")
+        .long("number-width")
+        .default_value("6"),
+    )
+    .arg(
+        Arg::with_name("number_separator")
+        .value_name("STR")
+        .help("Text between the line number and the line")
+        .long("number-separator")
+        .default_value("\t"),
+    )
+    .arg(
+        Arg::with_name("squeeze_blank")
+        .help("Suppress repeated empty output lines")
+        .short("s")
+        .long("squeeze-blank")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("show_all")
+        .help("Equivalent to -vET")
+        .short("A")
+        .long("show-all")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("e")
+        .help("Equivalent to -vE")
+        .short("e")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("show_ends")
+        .help("Display $ at end of each line")
+        .short("E")
+        .long("show-ends")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("t")
+        .help("Equivalent to -vT")
+        .short("t")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("show_tabs")
+        .help("Display TAB characters as ^I")
+        .short("T")
+        .long("show-tabs")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("show_nonprinting")
+        .help("Use ^ and M- notation, except for LFD and TAB")
+        .short("v")
+        .long("show-nonprinting")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("no_decompress")
+        .help("Copy compressed input as-is instead of decompressing it")
+        .long("no-decompress")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("follow")
+        .help("Keep printing data appended to the last FILE")
+        .short("f")
+        .long("follow")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("range")
+        .value_name("START:END")
+        .help("Only print these lines of each file (START:END, START:+COUNT, negative counts from the end)")
+        .long("range")
+        .alias("lines")
+        .allow_hyphen_values(true)
+        .multiple(true)
+        .number_of_values(1)
+        .conflicts_with("follow"),
+    )
+    .arg(
+        Arg::with_name("from_encoding")
+        .value_name("ENCODING")
+        .help("Convert input to UTF-8 from utf-16le, utf-16be, latin1 or windows-1252, or 'auto' to go by the byte order mark")
+        .long("from-encoding")
+        .takes_value(true)
+        .conflicts_with("follow"),
+    )
+    .arg(
+        Arg::with_name("eol")
+        .value_name("EOL")
+        .help("Line endings to write")
+        .long("eol")
+        .possible_values(&["lf", "crlf", "keep"])
+        .default_value("keep"),
+    )
+    .arg(
+        Arg::with_name("ensure_final_newline")
+        .help("Add a newline to the end of any file that lacks one")
+        .long("ensure-final-newline")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("hex")
+        .help("Display input as a canonical hex+ASCII dump, like hexdump -C")
+        .long("hex")
+        .takes_value(false)
+        .conflicts_with_all(&[
+            "follow", "range", "headers", "separator", "null_separated", "from_encoding", "number",
+            "number_nonblank", "number_per_file", "squeeze_blank", "show_all", "e", "show_ends", "t",
+            "show_tabs", "show_nonprinting", "eol", "ensure_final_newline",
+        ]),
+    )
+    .arg(
+        Arg::with_name("plain")
+        .help("With --hex, write plain hex like xxd -p")
+        .long("plain")
+        .takes_value(false)
+        .requires("hex"),
+    )
+    .arg(
+        Arg::with_name("reverse")
+        .help("With --hex, turn a hex dump back into bytes")
+        .long("reverse")
+        .takes_value(false)
+        .requires("hex"),
+    )
+    .arg(
+        Arg::with_name("prefetch")
+        .value_name("N")
+        .help("Read up to N files ahead on worker threads while writing the current one")
+        .long("prefetch")
+        .default_value("0"),
+    )
+    .arg(
+        Arg::with_name("output")
+        .value_name("PATH")
+        .help("Write to PATH, replacing it only once all input has been read")
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .conflicts_with("follow"),
+    )
+    .arg(
+        Arg::with_name("tee")
+        .value_name("PATH")
+        .help("Also write the output to PATH, replacing its contents")
+        .long("tee")
+        .multiple(true)
+        .number_of_values(1),
+    )
+    .arg(
+        Arg::with_name("tee_append")
+        .value_name("PATH")
+        .help("Also write the output to the end of PATH")
+        .long("tee-append")
+        .multiple(true)
+        .number_of_values(1),
+    )
+    .arg(
+        Arg::with_name("headers")
+        .help("Print a ==> FILE <== header before each file")
+        .long("headers")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("separator")
+        .value_name("STR")
+        .help("Print STR between files")
+        .long("separator")
+        .allow_hyphen_values(true)
+        .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("null_separated")
+        .help("Print a NUL byte between files")
+        .long("null-separated")
+        .takes_value(false)
+        .conflicts_with("separator"),
+    )
+    .arg(
+        Arg::with_name("recursive")
+        .help("Print the files in directories and their subdirectories, in sorted order")
+        .short("r")
+        .long("recursive")
+        .takes_value(false),
+    )
+    .arg(
+        Arg::with_name("include")
+        .value_name("GLOB")
+        .help("With -r, only print files matching GLOB (a GLOB without / matches file names)")
+        .long("include")
+        .multiple(true)
+        .number_of_values(1)
+        .requires("recursive"),
+    )
+    .arg(
+        Arg::with_name("exclude")
+        .value_name("GLOB")
+        .help("With -r, skip files and directories matching GLOB")
+        .long("exclude")
+        .multiple(true)
+        .number_of_values(1)
+        .requires("recursive"),
+    )
+    .arg(
+        Arg::with_name("gitignore")
+        .help("With -r, skip files ignored by .gitignore")
+        .long("gitignore")
+        .takes_value(false)
+        .requires("recursive"),
+    )
+    .arg(
+        Arg::with_name("binary")
+        .help("With -r, print binary files too instead of skipping them")
+        .long("binary")
+        .takes_value(false)
+        .requires("recursive"),
+    );
+
+    #[cfg(feature = "pretty")]
+    let app = app.arg(
+        Arg::with_name("pretty")
+        .help("Syntax-highlight and page the output when it goes to a terminal")
+        .long("pretty")
+        .takes_value(false)
+        .conflicts_with_all(&[
+            "follow", "output", "squeeze_blank", "range", "show_all", "e", "show_ends", "t",
+            "show_tabs", "show_nonprinting", "eol", "ensure_final_newline", "hex", "headers",
+            "separator", "null_separated", "tee", "tee_append",
+        ]),
+    );
+
+    let matches = app.get_matches();
+
+    let show_all = matches.is_present("show_all");
+    let e = matches.is_present("e");
+    let t = matches.is_present("t");
+
+    let number_start = matches
+        .value_of("number_start")
+        .map(parse_int)
+        .transpose()
+        .map_err(|e| format!("illegal number start -- {}", e))?;
+
+    let number_width = matches
+        .value_of("number_width")
+        .map(parse_positive_int)
+        .transpose()
+        .map_err(|e| format!("illegal number width -- {}", e))?;
+
+    let ranges = match matches.values_of("range") {
+        Some(values) => {
+            let mut ranges = Vec::new();
+            for val in values {
+                ranges.extend(range::parse_ranges(val).map_err(|e| format!("illegal range -- {}", e))?);
+            }
+            Some(ranges)
+        }
+        None => None,
+    };
+
+    let prefetch = matches
+        .value_of("prefetch")
+        .map(parse_int)
+        .transpose()
+        .map_err(|e| format!("illegal prefetch count -- {}", e))?;
+
+    let encoding = matches
+        .value_of("from_encoding")
+        .map(encoding::parse_encoding)
+        .transpose()
+        .map_err(|e| format!("unknown encoding -- {}", e))?;
+
+    let globs = |name| {
+        walk::Globs::new(matches.values_of(name).into_iter().flatten())
+            .map_err(|e| format!("illegal glob -- {}", e))
+    };
+    let includes = globs("include")?;
+    let excludes = globs("exclude")?;
+
+    let manifest = match (matches.value_of("files_from"), matches.value_of("files0_from")) {
+        (Some(path), _) => Some((path, b'\n')),
+        (_, Some(path)) => Some((path, b'\0')),
+        _ => None,
+    };
+    // Without FILE arguments a manifest replaces the default of stdin.
+    let mut files = Vec::new();
+    if matches.occurrences_of("files") > 0 || manifest.is_none() {
+        files = matches.values_of_lossy("files").unwrap();
+    }
+    let mut origins = vec![None; files.len()];
+    let mut manifest_errors = Vec::new();
+    if let Some((path, separator)) = manifest {
+        for entry in manifest::read(path, separator)? {
+            match entry {
+                Ok(entry) => {
+                    files.push(entry.filename);
+                    origins.push(Some(format!("{}:{}", path, entry.line)));
+                }
+                Err(err) => manifest_errors.push(err),
+            }
+        }
+    }
 
     Ok(Config {
-        files: matches.values_of_lossy("files").unwrap(),
+        files,
+        origins,
+        manifest_errors,
         number_lines: matches.is_present("number"),
         number_nonblank_lines: matches.is_present("number_nonblank"),
+        show_ends: show_all || e || matches.is_present("show_ends"),
+        show_tabs: show_all || t || matches.is_present("show_tabs"),
+        show_nonprinting: show_all || e || t || matches.is_present("show_nonprinting"),
+        squeeze_blank: matches.is_present("squeeze_blank"),
+        number_per_file: matches.is_present("number_per_file"),
+        number_start: number_start.unwrap(),
+        number_width: number_width.unwrap(),
+        number_separator: matches.value_of_lossy("number_separator").unwrap().into_owned(),
+        decompress: !matches.is_present("no_decompress"),
+        follow: matches.is_present("follow"),
+        ranges,
+        encoding,
+        eol: match matches.value_of("eol") {
+            Some("lf") => Eol::Lf,
+            Some("crlf") => Eol::Crlf,
+            _ => Eol::Keep,
+        },
+        ensure_final_newline: matches.is_present("ensure_final_newline"),
+        hex: matches.is_present("hex"),
+        hex_plain: matches.is_present("plain"),
+        hex_reverse: matches.is_present("reverse"),
+        prefetch: prefetch.unwrap(),
+        output: matches.value_of("output").map(String::from),
+        tees: tee_targets(&matches),
+        headers: matches.is_present("headers"),
+        recursive: matches.is_present("recursive"),
+        includes,
+        excludes,
+        gitignore: matches.is_present("gitignore"),
+        binary: matches.is_present("binary"),
+        separator: match matches.is_present("null_separated") {
+            true => Some("\0".to_string()),
+            false => matches.value_of("separator").map(String::from),
+        },
+        #[cfg(feature = "pretty")]
+        pretty: matches.is_present("pretty"),
     })
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
+/// Options for printing inputs from code, the way `catr` does on the
+/// command line.
+///
+/// ```no_run
+/// let mut out = Vec::new();
+/// catr::CatOptions::new()
+///     .number(true)
+///     .squeeze_blank(true)
+///     .cat_to(&["notes.txt"], &mut out)
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct CatOptions {
+    config: Config,
+}
+
+impl Default for CatOptions {
+    fn default() -> Self {
+        CatOptions {
+            config: Config {
+                files: Vec::new(),
+                origins: Vec::new(),
+                manifest_errors: Vec::new(),
+                number_lines: false,
+                number_nonblank_lines: false,
+                show_ends: false,
+                show_tabs: false,
+                show_nonprinting: false,
+                squeeze_blank: false,
+                number_per_file: false,
+                number_start: 1,
+                number_width: 6,
+                number_separator: "\t".to_string(),
+                decompress: true,
+                follow: false,
+                ranges: None,
+                encoding: None,
+                eol: Eol::Keep,
+                ensure_final_newline: false,
+                hex: false,
+                hex_plain: false,
+                hex_reverse: false,
+                prefetch: 0,
+                output: None,
+                tees: Vec::new(),
+                headers: false,
+                separator: None,
+                recursive: false,
+                includes: walk::Globs::default(),
+                excludes: walk::Globs::default(),
+                gitignore: false,
+                binary: false,
+                #[cfg(feature = "pretty")]
+                pretty: false,
+            },
+        }
+    }
+}
+
+impl CatOptions {
+    /// Options that copy the inputs unchanged, like `catr` without flags.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number all output lines (-n). Turns off `number_nonblank`.
+    pub fn number(mut self, yes: bool) -> Self {
+        self.config.number_lines = yes;
+        if yes {
+            self.config.number_nonblank_lines = false;
+        }
+        self
+    }
+
+    /// Number nonempty output lines (-b). Turns off `number`.
+    pub fn number_nonblank(mut self, yes: bool) -> Self {
+        self.config.number_nonblank_lines = yes;
+        if yes {
+            self.config.number_lines = false;
+        }
+        self
+    }
+
+    /// Restart line numbers for each input (--number-per-file).
+    pub fn number_per_file(mut self, yes: bool) -> Self {
+        self.config.number_per_file = yes;
+        self
+    }
+
+    /// First line number (--number-start).
+    pub fn number_start(mut self, start: usize) -> Self {
+        self.config.number_start = start;
+        self
+    }
+
+    /// Width of the line number column (--number-width).
+    pub fn number_width(mut self, width: usize) -> Self {
+        self.config.number_width = width;
+        self
+    }
+
+    /// Text between the line number and the line (--number-separator).
+    pub fn number_separator(mut self, separator: impl Into<String>) -> Self {
+        self.config.number_separator = separator.into();
+        self
+    }
+
+    /// Display $ at the end of each line (-E).
+    pub fn show_ends(mut self, yes: bool) -> Self {
+        self.config.show_ends = yes;
+        self
+    }
+
+    /// Display TAB characters as ^I (-T).
+    pub fn show_tabs(mut self, yes: bool) -> Self {
+        self.config.show_tabs = yes;
+        self
+    }
+
+    /// Use ^ and M- notation, except for LFD and TAB (-v).
+    pub fn show_nonprinting(mut self, yes: bool) -> Self {
+        self.config.show_nonprinting = yes;
+        self
+    }
+
+    /// Suppress repeated empty output lines (-s).
+    pub fn squeeze_blank(mut self, yes: bool) -> Self {
+        self.config.squeeze_blank = yes;
+        self
+    }
+
+    /// Decompress gzip, bzip2, xz and zstd input (on by default).
+    pub fn decompress(mut self, yes: bool) -> Self {
+        self.config.decompress = yes;
+        self
+    }
+
+    /// Only print these lines of each input, in the --range syntax. Can be
+    /// given more than once.
+    pub fn range(mut self, spec: &str) -> MyResult<Self> {
+        let ranges = range::parse_ranges(spec)
+            .map_err(|e| format!("illegal range -- {}", e))?;
+        self.config.ranges.get_or_insert_with(Vec::new).extend(ranges);
+        Ok(self)
+    }
+
+    /// Convert input to UTF-8 from the named encoding (--from-encoding).
+    pub fn from_encoding(mut self, name: &str) -> MyResult<Self> {
+        let encoding = encoding::parse_encoding(name)
+            .map_err(|e| format!("unknown encoding -- {}", e))?;
+        self.config.encoding = Some(encoding);
+        Ok(self)
+    }
+
+    /// Line endings to write (--eol).
+    pub fn eol(mut self, eol: Eol) -> Self {
+        self.config.eol = eol;
+        self
+    }
+
+    /// Add a newline to the end of any input that lacks one
+    /// (--ensure-final-newline).
+    pub fn ensure_final_newline(mut self, yes: bool) -> Self {
+        self.config.ensure_final_newline = yes;
+        self
+    }
+
+    /// Print a `==> name <==` header before each input (--headers).
+    pub fn headers(mut self, yes: bool) -> Self {
+        self.config.headers = yes;
+        self
+    }
+
+    /// Print `separator` between inputs (--separator). A NUL separator
+    /// gives --null-separated.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.config.separator = Some(separator.into());
+        self
+    }
+
+    /// Read up to `files` inputs ahead on worker threads (--prefetch).
+    pub fn prefetch(mut self, files: usize) -> Self {
+        self.config.prefetch = files;
+        self
+    }
+
+    /// Print the inputs in order to `out`; "-" reads stdin. Stops at the
+    /// first input that can't be read, with an error naming it.
+    pub fn cat_to<S: AsRef<str>, W: Write>(&self, inputs: &[S], out: &mut W) -> MyResult<()> {
+        let files: Vec<String> = inputs
+            .iter()
+            .map(|input| input.as_ref().to_string())
+            .collect();
+        cat_inputs(&files, &self.config, out, &[], |file_num, err| {
+            Err(format!("{}: {}", files[file_num], error_message(&*err)).into())
+        })
+    }
+}
+
+// The --tee and --tee-append files, in the order they were given.
+fn tee_targets(matches: &clap::ArgMatches) -> Vec<tee::TeeTarget> {
+    let mut targets = Vec::new();
+    for (name, append) in [("tee", false), ("tee_append", true)] {
+        let paths = matches.values_of(name).into_iter().flatten();
+        let indices = matches.indices_of(name).into_iter().flatten();
+        for (path, index) in paths.zip(indices) {
+            let path = path.to_string();
+            targets.push((index, tee::TeeTarget { path, append }));
+        }
+    }
+    targets.sort_by_key(|(index, _)| *index);
+    targets.into_iter().map(|(_, target)| target).collect()
+}
+
+impl Config {
+    // How an input is named in messages. One from a manifest is shown
+    // with the manifest line it came from.
+    fn input_name(&self, file_num: usize) -> String {
+        match self.origins.get(file_num) {
+            Some(Some(origin)) => format!("{}: {}", origin, self.files[file_num]),
+            _ => self.files[file_num].clone(),
+        }
+    }
+}
+
+fn parse_int(val: &str) -> MyResult<usize> {
+    val.parse().map_err(|_| From::from(val))
+}
+
+fn parse_positive_int(val: &str) -> MyResult<usize> {
+    match val.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(From::from(val)),
+    }
+}
+
+fn open(filename: &str, config: &Config) -> MyResult<Box<dyn BufRead>> {
     match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+        "-" => decode(BufReader::new(io::stdin()), config),
+        _ => decode(BufReader::new(File::open(filename)?), config),
+    }
+}
+
+// Decompress and transcode an input as the options ask.
+fn decode<R: BufRead + 'static>(reader: R, config: &Config) -> MyResult<Box<dyn BufRead>> {
+    let file = maybe_decompress(reader, config.decompress)?;
+    match config.encoding {
+        Some(encoding) => Ok(encoding::decoder(encoding, file)),
+        None => Ok(file),
+    }
+}
+
+// Put a decoder in front of the reader if its first bytes are the magic
+// number of a compressed format.
+fn maybe_decompress<R: BufRead + 'static>(
+    mut reader: R,
+    decompress: bool,
+) -> io::Result<Box<dyn BufRead>> {
+    if decompress {
+        let (format, head) = decompress::sniff_reader(&mut reader)?;
+        let reader = Cursor::new(head).chain(reader);
+        return match format {
+            Some(format) => decompress::decoder(format, reader),
+            None => Ok(Box::new(reader)),
+        };
     }
+    Ok(Box::new(reader))
 }
 
 // This will not compile and will error out " doesn't have a size known at compile-time"
@@ -62,29 +763,583 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
 //     }
 // }
 
-pub fn run(config: Config) -> MyResult<()> {
-    for file in config.files{
-        match open(&file) {
-            Err(err) => eprintln!("Failed to open file {}: {}", file, err),
-            Ok(file) => {
-                let mut last_num = 0;
-                for (line_num, line) in file.lines().enumerate() {
-                    let line = line?;
-                    if config.number_lines {
-                        println!("{:>6}\t{}",line_num + 1, line);
-                    } else if config.number_nonblank_lines {
-                        if line.is_empty() {
-                            println!();
-                        } else {
-                            last_num += 1;
-                            println!("{:>6}\t{}",last_num , line);
-                        }
-                    } else {
-                        println!("{}", line);
-                    }
+// Whether any option asks for per-line processing or transcoding instead
+// of a plain copy. Headers need to know whether a file ended mid-line.
+fn formats_lines(config: &Config) -> bool {
+    config.number_lines
+        || config.number_nonblank_lines
+        || config.show_ends
+        || config.show_tabs
+        || config.show_nonprinting
+        || config.squeeze_blank
+        || config.ranges.is_some()
+        || config.encoding.is_some()
+        || config.eol != Eol::Keep
+        || config.ensure_final_newline
+        || config.headers
+}
+
+// Rewrite the line ending of a record for --eol. A record without a
+// newline (the end of a file) is left alone.
+fn convert_eol(line: &mut Vec<u8>, eol: Eol) {
+    match eol {
+        Eol::Lf if line.ends_with(b"\r\n") => {
+            line.truncate(line.len() - 2);
+            line.push(b'\n');
+        }
+        Eol::Crlf if line.ends_with(b"\n") && !line.ends_with(b"\r\n") => {
+            line.truncate(line.len() - 1);
+            line.extend_from_slice(b"\r\n");
+        }
+        _ => {}
+    }
+}
+
+// Render a line the way GNU cat does for -v, -T and -E. The line is
+// a raw record from read_until and keeps its newline, if it had one.
+fn visualize(line: &[u8], config: &Config) -> Vec<u8> {
+    let (mut content, newline) = match line.strip_suffix(b"\n") {
+        Some(content) => (content, true),
+        None => (line, false),
+    };
+    // Like GNU cat, -E makes a CRLF ending visible as ^M$.
+    let mut crlf = false;
+    if newline && config.show_ends {
+        if let Some(stripped) = content.strip_suffix(b"\r") {
+            content = stripped;
+            crlf = true;
+        }
+    }
+    let mut out = Vec::with_capacity(line.len() + 1);
+    for &byte in content {
+        match byte {
+            b'\t' if config.show_tabs => out.extend_from_slice(b"^I"),
+            b'\t' => out.push(byte),
+            _ if !config.show_nonprinting => out.push(byte),
+            0..=31 => out.extend_from_slice(&[b'^', byte + 64]),
+            32..=126 => out.push(byte),
+            127 => out.extend_from_slice(b"^?"),
+            128..=159 => out.extend_from_slice(&[b'M', b'-', b'^', byte - 128 + 64]),
+            160..=254 => out.extend_from_slice(&[b'M', b'-', byte - 128]),
+            255 => out.extend_from_slice(b"M-^?"),
+        }
+    }
+    if crlf {
+        out.extend_from_slice(b"^M");
+    }
+    if newline {
+        if config.show_ends {
+            out.push(b'$');
+        }
+        out.push(b'\n');
+    }
+    out
+}
+
+// Copy an input to stdout untouched. The reader and writer are kept as
+// concrete types so that io::copy can hand the work to copy_file_range,
+// sendfile or splice on Linux; otherwise it goes through the large
+// BufReader buffer.
+fn copy_raw<W: Write>(
+    filename: &str,
+    out: &mut output::Tracked<W>,
+    decompress: bool,
+) -> MyResult<()> {
+    match filename {
+        "-" => {
+            let stdin = BufReader::with_capacity(COPY_BUF_SIZE, io::stdin().lock());
+            copy_reader(stdin, out, decompress, false)?;
+        }
+        _ => {
+            let file = BufReader::with_capacity(COPY_BUF_SIZE, File::open(filename)?);
+            copy_reader(file, out, decompress, true)?;
+        }
+    }
+    Ok(())
+}
+
+// io::copy can't say which side failed, and going through the tracking
+// writer would stop the kernel from doing the copy. An error on the first
+// read (a directory, say) is the input's; after that, if the input can
+// still be read, it was the output. Stdin isn't read again, as that could
+// block on a pipe, so a failure there is put down to the output.
+fn copy_reader<R: BufRead + 'static, W: Write>(
+    mut reader: R,
+    out: &mut output::Tracked<W>,
+    decompress: bool,
+    reread: bool,
+) -> io::Result<u64> {
+    let mut head = Vec::new();
+    if decompress {
+        let (format, peeked) = decompress::sniff_reader(&mut reader)?;
+        if let Some(format) = format {
+            let reader = Cursor::new(peeked).chain(reader);
+            return io::copy(&mut decompress::decoder(format, reader)?, out);
+        }
+        head = peeked;
+    } else {
+        reader.fill_buf()?;
+    }
+    // Bytes taken off the reader to sniff it go out first.
+    out.write_all(&head)?;
+    let result = io::copy(&mut reader, out.get_mut());
+    if result.is_err() && (!reread || reader.fill_buf().is_ok()) {
+        out.failed = true;
+    }
+    result.map(|n| n + head.len() as u64)
+}
+
+// Numbering and squeeze state carried from one line to the next, and
+// from one file to the next unless --number-per-file is given.
+struct LineState {
+    next_num: usize,
+    prev_blank: bool,
+    line_start: bool,
+    // Line number within the current file and the lines --range selects
+    file_line: usize,
+    selection: Option<Vec<RangeInclusive<usize>>>,
+}
+
+impl LineState {
+    fn new(config: &Config) -> Self {
+        LineState {
+            next_num: config.number_start,
+            prev_blank: false,
+            line_start: true,
+            file_line: 0,
+            selection: None,
+        }
+    }
+
+    fn selected(&self) -> bool {
+        match &self.selection {
+            Some(ranges) => ranges.iter().any(|range| range.contains(&self.file_line)),
+            None => true,
+        }
+    }
+
+    // Every selected range ends before the next line, so the rest of the
+    // file doesn't need to be read.
+    fn selection_done(&self) -> bool {
+        match &self.selection {
+            Some(ranges) => ranges.iter().all(|range| *range.end() <= self.file_line),
+            None => false,
+        }
+    }
+}
+
+// Count lines the way cat_lines sees them, including a final line that
+// has no newline.
+fn count_lines(mut file: impl BufRead) -> io::Result<usize> {
+    let mut count = 0;
+    let mut last = b'\n';
+    loop {
+        let buf = file.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        count += buf.iter().filter(|&&byte| byte == b'\n').count();
+        last = buf[buf.len() - 1];
+        let len = buf.len();
+        file.consume(len);
+    }
+    Ok(if last == b'\n' { count } else { count + 1 })
+}
+
+// Work out which lines of this input --range selects. Ranges counted from
+// the end need the number of lines first, so regular files are read twice
+// and anything else (stdin, a pipe, or an input that was prefetched) is
+// held in memory, as it can't be read again.
+fn select_ranges(
+    filename: &str,
+    in_memory: bool,
+    mut file: Box<dyn BufRead>,
+    ranges: &[LineRange],
+    config: &Config,
+    state: &mut LineState,
+) -> MyResult<Box<dyn BufRead>> {
+    if !range::needs_total(ranges) {
+        state.selection = Some(range::resolve(ranges, None));
+        return Ok(file);
+    }
+    let regular = filename != "-" && std::fs::metadata(filename)?.is_file();
+    if in_memory || !regular {
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        state.selection = Some(range::resolve(ranges, Some(count_lines(&buf[..])?)));
+        return Ok(Box::new(Cursor::new(buf)));
+    }
+    state.selection = Some(range::resolve(ranges, Some(count_lines(file)?)));
+    open(filename, config)
+}
+
+fn cat_lines(
+    mut file: impl BufRead,
+    out: &mut impl Write,
+    config: &Config,
+    state: &mut LineState,
+) -> MyResult<()> {
+    let mut line = Vec::new();
+    while !state.selection_done() {
+        line.clear();
+        if file.read_until(b'\n', &mut line)? == 0 {
+            break; // eof
+        }
+        // A record that continues an unterminated line from the previous
+        // file is neither blank nor numbered, as in GNU cat.
+        let line_start = state.line_start;
+        state.line_start = line.ends_with(b"\n");
+        state.file_line += 1;
+        // Lines outside --range still count, so numbers match the file.
+        let selected = state.selected();
+        let blank = line_start && line == b"\n";
+        if config.squeeze_blank && blank && state.prev_blank {
+            continue;
+        }
+        state.prev_blank = blank;
+        if line_start && (config.number_lines || (config.number_nonblank_lines && !blank)) {
+            if selected {
+                write!(
+                    out,
+                    "{:>width$}{}",
+                    state.next_num,
+                    config.number_separator,
+                    width = config.number_width,
+                )?;
+            }
+            state.next_num += 1;
+        }
+        if selected {
+            convert_eol(&mut line, config.eol);
+            out.write_all(&visualize(&line, config))?;
+        }
+    }
+    Ok(())
+}
+
+// Print one input, or follow it when it is the last file and --follow
+// was given. `prefetched` holds the contents when a worker has already
+// read the file.
+fn cat_file<W: Write>(
+    filename: &str,
+    prefetched: Option<Vec<u8>>,
+    follow: bool,
+    out: &mut output::Tracked<W>,
+    config: &Config,
+    state: &mut LineState,
+) -> MyResult<()> {
+    if follow {
+        if config.number_per_file {
+            state.next_num = config.number_start;
+        }
+        return follow::follow(filename, out, config, state);
+    }
+    let in_memory = prefetched.is_some();
+    if !formats_lines(config) {
+        return match prefetched {
+            Some(data) => copy_reader(Cursor::new(data), out, config.decompress, true)
+                .map(drop)
+                .map_err(From::from),
+            None => copy_raw(filename, out, config.decompress),
+        };
+    }
+    let file = match prefetched {
+        Some(data) => decode(Cursor::new(data), config)?,
+        None => open(filename, config)?,
+    };
+    // Numbers restart per file with --range so they are the line numbers
+    // of the file itself.
+    if config.number_per_file || config.ranges.is_some() {
+        state.next_num = config.number_start;
+    }
+    state.file_line = 0;
+    let file = match &config.ranges {
+        Some(ranges) => select_ranges(filename, in_memory, file, ranges, config, state)?,
+        None => file,
+    };
+    cat_lines(file, out, config, state)?;
+    if config.ensure_final_newline && !state.line_start {
+        let newline: &[u8] = match config.eol {
+            Eol::Crlf => b"\r\n",
+            _ => b"\n",
+        };
+        out.write_all(&visualize(newline, config))?;
+        state.line_start = true;
+    }
+    Ok(())
+}
+
+// The text of an error as GNU tools print it, without the "(os error N)"
+// that Rust appends to system errors.
+fn error_message(err: &(dyn Error + 'static)) -> String {
+    let msg = err.to_string();
+    match err.downcast_ref::<io::Error>().and_then(io::Error::raw_os_error) {
+        Some(code) => msg.trim_end_matches(&format!(" (os error {})", code)).to_string(),
+        None => msg,
+    }
+}
+
+// Identifies the file behind a name or descriptor.
+#[cfg(unix)]
+fn file_id(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+// Write what goes before an input with --separator and --headers: the
+// separator (or, with only --headers, a blank line) between inputs, then
+// a "==> name <==" header as headr prints it. Headers always start on a
+// line of their own. The input starts on a fresh line, and a blank line
+// at its top is never squeezed.
+fn write_banner(
+    filename: &str,
+    first: bool,
+    out: &mut impl Write,
+    config: &Config,
+    state: &mut LineState,
+) -> io::Result<()> {
+    let newline = match config.eol {
+        Eol::Crlf => "\r\n",
+        _ => "\n",
+    };
+    if !first {
+        if config.headers && !state.line_start {
+            out.write_all(newline.as_bytes())?;
+        }
+        match &config.separator {
+            Some(separator) => {
+                out.write_all(separator.as_bytes())?;
+                if config.headers && !separator.ends_with('\n') {
+                    out.write_all(newline.as_bytes())?;
                 }
-            },
+            }
+            None => out.write_all(newline.as_bytes())?,
+        }
+    }
+    if config.headers {
+        write!(out, "==> {} <=={}", filename, newline)?;
+    }
+    state.line_start = true;
+    state.prev_blank = false;
+    Ok(())
+}
+
+// Print every input to `out` in order. Each input that fails is handed to
+// `on_error`; returning an error from there stops the whole run. Inputs
+// that are one of the `outputs` files are refused. A failure writing
+// `out` stops the run with a WriteError.
+fn cat_inputs<W: Write>(
+    files: &[String],
+    config: &Config,
+    out: &mut W,
+    outputs: &[(u64, u64)],
+    mut on_error: impl FnMut(usize, Box<dyn Error>) -> MyResult<()>,
+) -> MyResult<()> {
+    let mut out = output::Tracked::new(out);
+    let mut state = LineState::new(config);
+    let mut started = false;
+    // A followed file is left out of the read-ahead; it is read as it grows.
+    let mut prefetcher = match config.prefetch {
+        0 => None,
+        n => {
+            let mut files = files.to_vec();
+            if config.follow {
+                files.pop();
+            }
+            Some(prefetch::Prefetcher::new(files, n))
+        }
+    };
+    for (file_num, filename) in files.iter().enumerate() {
+        let last = file_num + 1 == files.len();
+        let follow = config.follow && last && filename != "-";
+        let prefetched = match &mut prefetcher {
+            Some(prefetcher) if !(config.follow && last) => prefetcher.next(),
+            _ => None,
+        };
+        if output::is_output(filename, outputs) {
+            on_error(file_num, From::from("input file is output file"))?;
+            continue;
+        }
+        if config.headers || config.separator.is_some() {
+            // Like headr, an input that can't be opened gets no banner.
+            if prefetched.is_none() && filename != "-" {
+                if let Err(err) = File::open(filename) {
+                    on_error(file_num, err.into())?;
+                    continue;
+                }
+            }
+            if let Err(err) = write_banner(filename, !started, &mut out, config, &mut state) {
+                return Err(output::WriteError(err.into()).into());
+            }
+        }
+        started = true;
+        if let Err(err) = cat_file(filename, prefetched, follow, &mut out, config, &mut state) {
+            if out.failed {
+                return Err(output::WriteError(err).into());
+            }
+            on_error(file_num, err)?;
         }
     }
+    out.flush().map_err(|err| output::WriteError(err.into()))?;
     Ok(())
-}
\ No newline at end of file
+}
+
+fn is_broken_pipe(err: &(dyn Error + 'static)) -> bool {
+    if let Some(err) = err.downcast_ref::<output::WriteError>() {
+        return is_broken_pipe(&*err.0);
+    }
+    match err.downcast_ref::<io::Error>() {
+        Some(err) => err.kind() == io::ErrorKind::BrokenPipe,
+        None => false,
+    }
+}
+
+// Print or dump every input to `out`, reporting the ones that can't be
+// read on stderr. Returns false if any input failed.
+fn print_inputs<W: Write>(
+    config: &Config,
+    out: &mut W,
+    outputs: &[(u64, u64)],
+) -> MyResult<bool> {
+    if config.hex {
+        return hex::run(config, out, outputs);
+    }
+
+    let mut ok = true;
+    let result = cat_inputs(&config.files, config, out, outputs, |file_num, err| {
+        eprintln!("catr: {}: {}", config.input_name(file_num), error_message(&*err));
+        ok = false;
+        Ok(())
+    });
+    match result {
+        // Whoever reads our output has gone away, so stop quietly.
+        Err(err) if !is_broken_pipe(&*err) => Err(err),
+        _ => Ok(ok),
+    }
+}
+
+// Like print_inputs, but copying the output to the --tee files as well.
+// The plain writer is kept when there are none, so copies can still be
+// done by the kernel.
+fn print_and_tee<W: Write>(
+    config: &Config,
+    out: &mut W,
+    mut outputs: Vec<(u64, u64)>,
+) -> MyResult<bool> {
+    if config.tees.is_empty() {
+        return print_inputs(config, out, &outputs);
+    }
+    let mut tee = tee::Tee::new(out, &config.tees, &config.files);
+    outputs.extend(tee.ids());
+    let ok = print_inputs(config, &mut tee, &outputs)?;
+    match tee.finish() {
+        Ok(tees_ok) => Ok(tees_ok && ok),
+        // The tee files still got everything, but the main output was lost.
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(false),
+        Err(err) => Err(output::WriteError(err.into()).into()),
+    }
+}
+
+/// Prints every input, reporting the ones that can't be read on stderr
+/// and carrying on with the rest. Returns false if any input failed.
+pub fn run(mut config: Config) -> MyResult<bool> {
+    for err in &config.manifest_errors {
+        eprintln!("catr: {}", err);
+    }
+    let listed = config.manifest_errors.is_empty();
+    let walked = !config.recursive || walk::expand(&mut config);
+    match print_all(config) {
+        Ok(ok) => Ok(ok && listed && walked),
+        Err(err) if err.is::<output::WriteError>() => {
+            eprintln!("catr: {}", err);
+            Ok(false)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn print_all(config: Config) -> MyResult<bool> {
+    // Highlighting only makes sense for a person looking at a terminal;
+    // piped output stays plain.
+    #[cfg(feature = "pretty")]
+    {
+        use std::io::IsTerminal;
+        if config.pretty && io::stdout().is_terminal() {
+            return pretty::run(&config);
+        }
+    }
+
+    match &config.output {
+        // The inputs may include the output file, which is fine because
+        // it is only replaced at the end.
+        Some(path) => {
+            let report = |e: io::Error| format!("{}: {}", path, error_message(&e));
+            let mut file = output::AtomicFile::create(Path::new(path)).map_err(report)?;
+            let ok = print_and_tee(&config, file.writer(), Vec::new())?;
+            file.commit().map_err(report)?;
+            Ok(ok)
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            let outputs = output::stdout_id().into_iter().collect();
+            print_and_tee(&config, &mut out, outputs)
+        }
+    }
+}
+
+#[test]
+fn test_cat_to() {
+    let all = [
+        "tests/inputs/fox.txt",
+        "tests/inputs/spiders.txt",
+        "tests/inputs/the-bustle.txt",
+    ];
+    let mut out = Vec::new();
+    let res = CatOptions::new().number(true).cat_to(&all, &mut out);
+    assert!(res.is_ok());
+    assert_eq!(out, std::fs::read("tests/expected/all.n.out").unwrap());
+
+    let mut out = Vec::new();
+    let res = CatOptions::new()
+        .number_nonblank(true)
+        .range("4:+3")
+        .and_then(|options| options.cat_to(&["tests/inputs/the-bustle.txt"], &mut out));
+    assert!(res.is_ok());
+    assert_eq!(
+        out,
+        std::fs::read("tests/expected/the-bustle.txt.range4+3.b.out").unwrap()
+    );
+
+    let mut out = Vec::new();
+    let res = CatOptions::new()
+        .eol(Eol::Crlf)
+        .show_ends(true)
+        .cat_to(&["tests/inputs/fox.txt"], &mut out);
+    assert!(res.is_ok());
+    assert_eq!(out, b"The quick brown fox jumps over the lazy dog.^M$\n");
+}
+
+#[test]
+fn test_cat_to_bad_file() {
+    let mut out = Vec::new();
+    let res = CatOptions::new().cat_to(
+        &["tests/inputs/fox.txt", "tests/inputs/no-such-file.txt"],
+        &mut out,
+    );
+    assert!(res.is_err());
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "tests/inputs/no-such-file.txt: No such file or directory"
+    );
+    assert_eq!(out, std::fs::read("tests/inputs/fox.txt").unwrap());
+
+    let res = CatOptions::new().range("0:3");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "illegal range -- 0:3");
+}
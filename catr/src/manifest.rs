@@ -0,0 +1,93 @@
+use crate::{error_message, MyResult};
+use std::fs::File;
+use std::io::{self, Read};
+
+// One input named in a manifest, with the line it was on.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    pub filename: String,
+    pub line: usize,
+}
+
+// Read the list of inputs given with --files-from (one per line) or
+// --files0-from (NUL-terminated). "-" reads the list from stdin. A bad
+// entry comes back as its "MANIFEST:LINE: ..." message, so it can be
+// reported and skipped without losing the rest of the list.
+pub fn read(manifest: &str, separator: u8) -> MyResult<Vec<Result<Entry, String>>> {
+    let mut data = Vec::new();
+    let result = match manifest {
+        "-" => io::stdin().read_to_end(&mut data),
+        _ => File::open(manifest).and_then(|mut file| file.read_to_end(&mut data)),
+    };
+    result.map_err(|e| format!("{}: {}", manifest, error_message(&e)))?;
+    Ok(parse(&data, separator, manifest))
+}
+
+fn parse(data: &[u8], separator: u8, manifest: &str) -> Vec<Result<Entry, String>> {
+    let data = data.strip_suffix(&[separator]).unwrap_or(data);
+    if data.is_empty() {
+        return Vec::new();
+    }
+    data.split(|&byte| byte == separator)
+        .enumerate()
+        .map(|(i, name)| {
+            let line = i + 1;
+            let bad = |msg: &str| format!("{}:{}: {}", manifest, line, msg);
+            let filename =
+                std::str::from_utf8(name).map_err(|_| bad("file name is not valid UTF-8"))?;
+            match filename {
+                "" => Err(bad("invalid zero-length file name")),
+                "-" if manifest == "-" => Err(bad(
+                    "file name '-' not allowed when reading file names from stdin",
+                )),
+                _ => Ok(Entry {
+                    filename: filename.to_string(),
+                    line,
+                }),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse() {
+    let entry = |filename: &str, line| {
+        Ok(Entry {
+            filename: filename.to_string(),
+            line,
+        })
+    };
+    let res = parse(b"a.txt\nb c.txt\n", b'\n', "list");
+    assert_eq!(res, vec![entry("a.txt", 1), entry("b c.txt", 2)]);
+
+    // Newlines are part of the name with NUL separators, and the last
+    // terminator is optional
+    let res = parse(b"one\ntwo\0-", 0, "list");
+    assert_eq!(res, vec![entry("one\ntwo", 1), entry("-", 2)]);
+
+    let res = parse(b"", b'\n', "list");
+    assert_eq!(res, vec![]);
+
+    // Bad entries are reported in place, and the rest are still read
+    let res = parse(b"a.txt\n\nb.txt\n", b'\n', "list");
+    assert_eq!(
+        res,
+        vec![
+            entry("a.txt", 1),
+            Err("list:2: invalid zero-length file name".to_string()),
+            entry("b.txt", 3),
+        ]
+    );
+
+    let res = parse(b"a.txt\n-\n", b'\n', "-");
+    assert_eq!(
+        res[1],
+        Err("-:2: file name '-' not allowed when reading file names from stdin".to_string())
+    );
+
+    let res = parse(b"a.txt\0\xff\0", 0, "list");
+    assert_eq!(
+        res[1],
+        Err("list:2: file name is not valid UTF-8".to_string())
+    );
+}
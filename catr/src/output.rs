@@ -0,0 +1,168 @@
+use crate::{error_message, file_id};
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// An error writing the output. Unlike one reading an input it ends the
+// run, and it is reported once, as GNU cat does.
+#[derive(Debug)]
+pub struct WriteError(pub Box<dyn Error>);
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "write error: {}", error_message(&*self.0))
+    }
+}
+
+impl Error for WriteError {}
+
+// Passes writes through to the output and notes whether one failed, so
+// that an error can be put down to the output instead of the input that
+// was being read.
+pub struct Tracked<'a, W: Write> {
+    inner: &'a mut W,
+    pub failed: bool,
+}
+
+impl<'a, W: Write> Tracked<'a, W> {
+    pub fn new(inner: &'a mut W) -> Self {
+        Tracked {
+            inner,
+            failed: false,
+        }
+    }
+
+    // The output itself, for copies the kernel can do. Failures there
+    // have to be noted by the caller.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for Tracked<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf).inspect_err(|_| self.failed = true)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().inspect_err(|_| self.failed = true)
+    }
+}
+
+// A file written under a temporary name next to its destination and
+// renamed over it once everything has been written, so readers never see
+// a half-written file and the inputs can include the destination itself.
+pub struct AtomicFile {
+    writer: BufWriter<File>,
+    temp: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let dir = match path.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _ => Path::new("."),
+        };
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file name"))?
+            .to_string_lossy();
+        let mut attempt = 0;
+        let (file, temp) = loop {
+            let temp = dir.join(format!(".{}.catr-{}-{}", name, std::process::id(), attempt));
+            match OpenOptions::new().write(true).create_new(true).open(&temp) {
+                Ok(file) => break (file, temp),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+                Err(err) => return Err(err),
+            }
+        };
+        // Keep the mode of the file being replaced.
+        if let Ok(meta) = fs::metadata(path) {
+            let _ = file.set_permissions(meta.permissions());
+        }
+        Ok(AtomicFile {
+            writer: BufWriter::new(file),
+            temp,
+            path: path.to_path_buf(),
+            committed: false,
+        })
+    }
+
+    // Exposed as the concrete writer so that plain copies can still be
+    // done by the kernel.
+    pub fn writer(&mut self) -> &mut BufWriter<File> {
+        &mut self.writer
+    }
+
+    // Put the finished file in place of the destination.
+    pub fn commit(mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        fs::rename(&self.temp, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+// Identifies stdout when it is a regular file, which a shell redirection
+// may also have passed in as an input.
+#[cfg(unix)]
+pub fn stdout_id() -> Option<(u64, u64)> {
+    use std::os::fd::AsFd;
+    let fd = io::stdout().as_fd().try_clone_to_owned().ok()?;
+    let meta = File::from(fd).metadata().ok()?;
+    match meta.is_file() {
+        true => file_id(&meta),
+        false => None,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn stdout_id() -> Option<(u64, u64)> {
+    None
+}
+
+// Whether reading this input would read back what is being written to one
+// of the outputs. Like GNU cat, an empty file is fine, as there is nothing
+// to read.
+pub fn is_output(filename: &str, outputs: &[(u64, u64)]) -> bool {
+    if outputs.is_empty() {
+        return false;
+    }
+    let meta = match filename {
+        "-" => stdin_metadata(),
+        _ => fs::metadata(filename).ok(),
+    };
+    match meta {
+        Some(meta) => {
+            meta.is_file()
+                && meta.len() > 0
+                && file_id(&meta).is_some_and(|id| outputs.contains(&id))
+        }
+        None => false,
+    }
+}
+
+#[cfg(unix)]
+fn stdin_metadata() -> Option<fs::Metadata> {
+    use std::os::fd::AsFd;
+    let fd = io::stdin().as_fd().try_clone_to_owned().ok()?;
+    File::from(fd).metadata().ok()
+}
+
+#[cfg(not(unix))]
+fn stdin_metadata() -> Option<fs::Metadata> {
+    None
+}
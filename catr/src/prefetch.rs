@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+// Files bigger than this are left for the main thread to stream, so the
+// read-ahead never holds more than a few of these in memory.
+const MAX_PREFETCH_SIZE: u64 = 1024 * 1024;
+
+// Most worker threads to start, however far ahead reading goes. Reads of
+// small files are quick, so a few threads keep well ahead of the writer.
+const MAX_WORKERS: usize = 8;
+
+type Job = (String, Sender<Option<Vec<u8>>>);
+
+// Reads the next few files on a small pool of worker threads while the
+// current one is being written. Up to `ahead` files are queued; the pool
+// is no bigger than that, the number of files, or MAX_WORKERS. Results
+// are handed back strictly in the order the files were given, so output
+// is the same as reading them one by one.
+pub struct Prefetcher {
+    jobs: Option<Sender<Job>>,
+    pending: VecDeque<Receiver<Option<Vec<u8>>>>,
+    files: std::vec::IntoIter<String>,
+    ahead: usize,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Prefetcher {
+    pub fn new(files: Vec<String>, ahead: usize) -> Self {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        let mut workers = Vec::new();
+        for _ in 0..ahead.min(files.len()).min(MAX_WORKERS) {
+            let rx = Arc::clone(&rx);
+            let worker = thread::Builder::new().spawn(move || loop {
+                let job = rx.lock().unwrap().recv();
+                match job {
+                    Ok((filename, result)) => {
+                        let _ = result.send(read_small_file(&filename));
+                    }
+                    Err(_) => break, // no more jobs
+                }
+            });
+            // Make do with the threads there are. With none, every file
+            // is simply opened by the caller.
+            match worker {
+                Ok(worker) => workers.push(worker),
+                Err(_) => break,
+            }
+        }
+        Prefetcher {
+            jobs: (!workers.is_empty()).then_some(tx),
+            pending: VecDeque::new(),
+            files: files.into_iter(),
+            ahead,
+            workers,
+        }
+    }
+
+    // The contents of the next file, or None when the caller should open
+    // it as usual (stdin, large or special files, and anything that could
+    // not be read, so errors are reported in the normal way).
+    pub fn next(&mut self) -> Option<Vec<u8>> {
+        while self.pending.len() <= self.ahead {
+            let filename = match self.files.next() {
+                Some(filename) => filename,
+                None => break,
+            };
+            let (tx, rx) = mpsc::channel();
+            if let Some(jobs) = &self.jobs {
+                let _ = jobs.send((filename, tx));
+            }
+            self.pending.push_back(rx);
+        }
+        self.pending.pop_front()?.recv().ok().flatten()
+    }
+}
+
+impl Drop for Prefetcher {
+    fn drop(&mut self) {
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn read_small_file(filename: &str) -> Option<Vec<u8>> {
+    if filename == "-" {
+        return None;
+    }
+    let meta = fs::metadata(filename).ok()?;
+    if !meta.is_file() || meta.len() > MAX_PREFETCH_SIZE {
+        return None;
+    }
+    let mut buf = Vec::with_capacity(meta.len() as usize);
+    File::open(filename).ok()?.read_to_end(&mut buf).ok()?;
+    Some(buf)
+}
+
+#[test]
+fn test_pool_size() {
+    let files = vec!["a".to_string(), "b".to_string()];
+    let prefetcher = Prefetcher::new(files.clone(), 100_000);
+    assert_eq!(prefetcher.workers.len(), 2);
+    assert_eq!(prefetcher.ahead, 100_000);
+
+    let many = vec!["a".to_string(); 100];
+    let prefetcher = Prefetcher::new(many, 100_000);
+    assert_eq!(prefetcher.workers.len(), MAX_WORKERS);
+
+    let mut prefetcher = Prefetcher::new(files, 1);
+    assert_eq!(prefetcher.workers.len(), 1);
+    assert_eq!(prefetcher.next(), None);
+}
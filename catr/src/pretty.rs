@@ -0,0 +1,232 @@
+use crate::{error_message, open, Config, MyResult};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue, terminal,
+};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::as_24_bit_terminal_escaped;
+
+const THEME: &str = "base16-ocean.dark";
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+
+// Pick a syntax by file extension, then by the first line (a shebang or a
+// modeline), and fall back to plain text.
+fn find_syntax<'a>(syntaxes: &'a SyntaxSet, filename: &str, text: &str) -> &'a SyntaxReference {
+    let by_name = match filename {
+        "-" => None,
+        _ => syntaxes
+            .find_syntax_for_file(Path::new(filename))
+            .ok()
+            .flatten(),
+    };
+    by_name
+        .or_else(|| syntaxes.find_syntax_by_first_line(text.lines().next().unwrap_or("")))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text())
+}
+
+// Turn one file into highlighted terminal lines: a header with the file
+// name, then the source with an optional line number gutter. `num` is the
+// next line number, carried from file to file as in plain mode.
+fn render(
+    filename: &str,
+    text: &str,
+    num: &mut usize,
+    config: &Config,
+    syntaxes: &SyntaxSet,
+    theme: &Theme,
+) -> MyResult<Vec<String>> {
+    let gutter = config.number_lines || config.number_nonblank_lines;
+    let rule = "─".repeat(config.number_width + 2);
+    let mut lines = vec![
+        format!("{}{}┬{}{}", DIM, rule, "─".repeat(40), RESET),
+        format!(
+            "{}{:w$} │ {}File: {}",
+            DIM,
+            "",
+            RESET,
+            filename,
+            w = config.number_width
+        ),
+        format!("{}{}┼{}{}", DIM, rule, "─".repeat(40), RESET),
+    ];
+    let mut highlighter = HighlightLines::new(find_syntax(syntaxes, filename, text), theme);
+    for line in text.split_inclusive('\n') {
+        let ranges = highlighter.highlight_line(line, syntaxes)?;
+        let code = as_24_bit_terminal_escaped(&ranges, false);
+        let code = code.trim_end_matches(['\n', '\r']);
+        let blank = line.trim_end_matches(['\n', '\r']).is_empty();
+        let prefix = if config.number_lines || (config.number_nonblank_lines && !blank) {
+            let prefix = format!("{}{:>w$} │ {}", DIM, *num, RESET, w = config.number_width);
+            *num += 1;
+            prefix
+        } else if gutter {
+            format!("{}{:w$} │ {}", DIM, "", RESET, w = config.number_width)
+        } else {
+            String::new()
+        };
+        lines.push(format!("{}{}{}", prefix, code, RESET));
+    }
+    lines.push(format!("{}{}┴{}{}", DIM, rule, "─".repeat(40), RESET));
+    Ok(lines)
+}
+
+// The pager's hold on the terminal: raw mode, the alternate screen, a
+// hidden cursor and line wrap turned off. Dropping it puts everything
+// back, however the pager comes to an end.
+struct PagerScreen;
+
+impl PagerScreen {
+    fn enter(out: &mut impl Write) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let screen = PagerScreen;
+        // Long lines are cut off at the edge of the screen instead of
+        // wrapping, so each line takes one row.
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+        write!(out, "\x1b[?7l")?;
+        Ok(screen)
+    }
+}
+
+impl Drop for PagerScreen {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        let _ = write!(out, "\x1b[?7h");
+        let _ = execute!(out, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// Show the lines one screen at a time, like less. Output that fits on
+// the screen is printed directly.
+fn page(lines: &[String]) -> io::Result<()> {
+    let (_, rows) = terminal::size()?;
+    let height = usize::from(rows.saturating_sub(1)).max(1);
+    let mut out = io::stdout().lock();
+    if lines.len() <= height {
+        for line in lines {
+            writeln!(out, "{}", line)?;
+        }
+        return Ok(());
+    }
+
+    let _screen = PagerScreen::enter(&mut out)?;
+    let last_top = lines.len() - height;
+    let mut top = 0;
+    loop {
+        queue!(
+            out,
+            cursor::MoveTo(0, 0),
+            terminal::Clear(terminal::ClearType::All)
+        )?;
+        for (row, line) in lines[top..top + height].iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, row as u16))?;
+            write!(out, "{}", line)?;
+        }
+        queue!(out, cursor::MoveTo(0, height as u16))?;
+        write!(
+            out,
+            "{}lines {}-{} of {} (q to quit){}",
+            DIM,
+            top + 1,
+            top + height,
+            lines.len(),
+            RESET
+        )?;
+        out.flush()?;
+
+        let key = match event::read()? {
+            Event::Key(key) => key,
+            _ => continue,
+        };
+        top = match key {
+            KeyEvent {
+                code: KeyCode::Char('q'),
+                ..
+            }
+            | KeyEvent {
+                code: KeyCode::Esc, ..
+            } => {
+                return Ok(());
+            }
+            KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => return Ok(()),
+            KeyEvent {
+                code: KeyCode::Char(' ') | KeyCode::PageDown | KeyCode::Char('f'),
+                ..
+            } => top + height,
+            KeyEvent {
+                code: KeyCode::Char('b') | KeyCode::PageUp,
+                ..
+            } => top.saturating_sub(height),
+            KeyEvent {
+                code: KeyCode::Char('j') | KeyCode::Down | KeyCode::Enter,
+                ..
+            } => top + 1,
+            KeyEvent {
+                code: KeyCode::Char('k') | KeyCode::Up,
+                ..
+            } => top.saturating_sub(1),
+            KeyEvent {
+                code: KeyCode::Char('g') | KeyCode::Home,
+                ..
+            } => 0,
+            KeyEvent {
+                code: KeyCode::Char('G') | KeyCode::End,
+                ..
+            } => last_top,
+            _ => top,
+        }
+        .min(last_top);
+    }
+}
+
+// Highlight every input and show the result in the pager. Inputs that
+// can't be read are reported like in plain mode. Returns false if any
+// input failed.
+pub fn run(config: &Config) -> MyResult<bool> {
+    let syntaxes = SyntaxSet::load_defaults_newlines();
+    let themes = ThemeSet::load_defaults();
+    let theme = &themes.themes[THEME];
+    let mut lines = Vec::new();
+    let mut ok = true;
+    let mut num = config.number_start;
+    for (file_num, filename) in config.files.iter().enumerate() {
+        if config.number_per_file {
+            num = config.number_start;
+        }
+        let rendered = open(filename, config).and_then(|mut file| {
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            render(
+                filename,
+                &String::from_utf8_lossy(&bytes),
+                &mut num,
+                config,
+                &syntaxes,
+                theme,
+            )
+        });
+        match rendered {
+            Ok(rendered) => lines.extend(rendered),
+            Err(err) => {
+                eprintln!(
+                    "catr: {}: {}",
+                    config.input_name(file_num),
+                    error_message(&*err)
+                );
+                ok = false;
+            }
+        }
+    }
+    page(&lines)?;
+    Ok(ok)
+}
@@ -0,0 +1,139 @@
+use crate::MyResult;
+use std::ops::RangeInclusive;
+
+// One end of a --range spec: a 1-based line number, or a position counted
+// back from the last line (FromEnd(1) is the last line).
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bound {
+    FromStart(usize),
+    FromEnd(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum End {
+    Line(Bound),
+    Count(usize),
+    Last,
+}
+
+// A parsed START:END, START:+COUNT or single-line range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineRange {
+    start: Bound,
+    end: End,
+}
+
+fn parse_bound(val: &str) -> Option<Bound> {
+    match val.strip_prefix('-') {
+        Some(num) => match num.parse() {
+            Ok(n) if n > 0 => Some(Bound::FromEnd(n)),
+            _ => None,
+        },
+        None => match val.parse() {
+            Ok(n) if n > 0 => Some(Bound::FromStart(n)),
+            _ => None,
+        },
+    }
+}
+
+// Parse a comma-separated list of ranges, such as "500:620,-10:".
+pub fn parse_ranges(val: &str) -> MyResult<Vec<LineRange>> {
+    val.split(',')
+        .map(|spec| parse_range(spec).ok_or_else(|| From::from(spec)))
+        .collect()
+}
+
+fn parse_range(spec: &str) -> Option<LineRange> {
+    let (start, end) = match spec.split_once(':') {
+        None => {
+            let line = parse_bound(spec)?;
+            return Some(LineRange {
+                start: line,
+                end: End::Line(line),
+            });
+        }
+        Some(parts) => parts,
+    };
+    let start = match start {
+        "" => Bound::FromStart(1),
+        _ => parse_bound(start)?,
+    };
+    let end = match end {
+        "" => End::Last,
+        _ => match end.strip_prefix('+') {
+            Some(count) => End::Count(count.parse().ok()?),
+            None => End::Line(parse_bound(end)?),
+        },
+    };
+    Some(LineRange { start, end })
+}
+
+// Whether resolving the ranges needs the number of lines in the input.
+pub fn needs_total(ranges: &[LineRange]) -> bool {
+    let from_end = |bound: Bound| matches!(bound, Bound::FromEnd(_));
+    ranges.iter().any(|range| {
+        from_end(range.start) || matches!(range.end, End::Line(bound) if from_end(bound))
+    })
+}
+
+// Turn the ranges into 1-based inclusive line numbers for one input.
+// `total` must be given when needs_total() says so.
+pub fn resolve(ranges: &[LineRange], total: Option<usize>) -> Vec<RangeInclusive<usize>> {
+    let line = |bound: Bound| match bound {
+        Bound::FromStart(n) => n,
+        Bound::FromEnd(n) => (total.unwrap_or(0) + 1).saturating_sub(n),
+    };
+    ranges
+        .iter()
+        .map(|range| {
+            let start = line(range.start).max(1);
+            let end = match range.end {
+                End::Line(bound) => line(bound),
+                End::Count(count) => start.saturating_add(count).saturating_sub(1),
+                End::Last => usize::MAX,
+            };
+            start..=end
+        })
+        .filter(|range| !range.is_empty())
+        .collect()
+}
+
+#[test]
+fn test_parse_ranges() {
+    let res = parse_ranges("500:620");
+    assert!(res.is_ok());
+    assert_eq!(resolve(&res.unwrap(), None), vec![500..=620]);
+
+    let res = parse_ranges("5:+3,10,:2,20:");
+    assert!(res.is_ok());
+    assert_eq!(
+        resolve(&res.unwrap(), None),
+        vec![5..=7, 10..=10, 1..=2, 20..=usize::MAX]
+    );
+
+    // Counting from the end needs the total
+    let res = parse_ranges("-3:-1,2:-8").unwrap();
+    assert!(needs_total(&res));
+    assert_eq!(resolve(&res, Some(10)), vec![8..=10, 2..=3]);
+
+    // More lines from the end than there are lines clamps to line 1,
+    // and ranges that end before they start select nothing
+    let res = parse_ranges("-20:2,5:3,4:+0").unwrap();
+    assert_eq!(resolve(&res, Some(10)), vec![1..=2]);
+
+    // A count that runs past the largest line number stops there
+    let res = parse_ranges("5:+18446744073709551615").unwrap();
+    assert_eq!(resolve(&res, None), vec![5..=usize::MAX - 1]);
+
+    // Line numbers start at 1
+    let res = parse_ranges("0:5");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "0:5");
+
+    let res = parse_ranges("1:5,foo");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "foo");
+
+    let res = parse_ranges("3:+x");
+    assert!(res.is_err());
+}
@@ -0,0 +1,151 @@
+use crate::{error_message, file_id, output};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+
+// A file given with --tee or --tee-append.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeeTarget {
+    pub path: String,
+    pub append: bool,
+}
+
+struct Destination {
+    path: String,
+    file: Option<File>,
+}
+
+// Sends everything written to the main output on to each tee file as
+// well. A tee file that fails is reported and dropped, and the others
+// carry on. The main output is one more destination: its error is kept
+// for finish() while the tee files go on being written, and is only
+// returned from a write once there is nowhere left to write to. Tee files
+// are written unbuffered, like tee(1), so one that is also an input is
+// seen with everything written to it so far.
+pub struct Tee<'a, W: Write> {
+    out: &'a mut W,
+    out_failed: bool,
+    out_error: Option<io::Error>,
+    destinations: Vec<Destination>,
+    ok: bool,
+}
+
+impl<'a, W: Write> Tee<'a, W> {
+    // Open the targets. One that would be truncated while it is also among
+    // the `inputs` is refused instead, before anything is lost; with
+    // --tee-append it is the input that gets refused later on.
+    pub fn new(out: &'a mut W, targets: &[TeeTarget], inputs: &[String]) -> Self {
+        let mut ok = true;
+        let destinations = targets
+            .iter()
+            .map(|target| {
+                if !target.append && is_input(&target.path, inputs) {
+                    eprintln!("catr: {}: input file is output file", target.path);
+                    ok = false;
+                    return Destination {
+                        path: target.path.clone(),
+                        file: None,
+                    };
+                }
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .append(target.append)
+                    .truncate(!target.append)
+                    .open(&target.path);
+                let file = match file {
+                    Ok(file) => Some(file),
+                    Err(err) => {
+                        eprintln!("catr: {}: {}", target.path, error_message(&err));
+                        ok = false;
+                        None
+                    }
+                };
+                Destination {
+                    path: target.path.clone(),
+                    file,
+                }
+            })
+            .collect();
+        Tee {
+            out,
+            out_failed: false,
+            out_error: None,
+            destinations,
+            ok,
+        }
+    }
+
+    // The files behind the tee targets, which must not be read as input.
+    pub fn ids(&self) -> Vec<(u64, u64)> {
+        self.destinations
+            .iter()
+            .filter_map(|dest| dest.file.as_ref()?.metadata().ok())
+            .filter_map(|meta| file_id(&meta))
+            .collect()
+    }
+
+    // Whether every tee file was opened and written without error, or
+    // the error that stopped the main output.
+    pub fn finish(self) -> io::Result<bool> {
+        match self.out_error {
+            Some(err) => Err(err),
+            None => Ok(self.ok),
+        }
+    }
+
+    fn write_out(&mut self, f: impl FnOnce(&mut W) -> io::Result<()>) {
+        if !self.out_failed {
+            if let Err(err) = f(self.out) {
+                self.out_failed = true;
+                self.out_error = Some(err);
+            }
+        }
+    }
+
+    // Once the main output and every tee file have failed, hand the main
+    // output's error back so the caller stops.
+    fn check_lost(&mut self) -> io::Result<()> {
+        let lost = self.out_failed && self.destinations.iter().all(|dest| dest.file.is_none());
+        match self.out_error.take() {
+            Some(err) if lost => Err(err),
+            err => {
+                self.out_error = err;
+                Ok(())
+            }
+        }
+    }
+
+    fn each(&mut self, mut f: impl FnMut(&mut File) -> io::Result<()>) {
+        for dest in &mut self.destinations {
+            if let Some(file) = &mut dest.file {
+                if let Err(err) = f(file) {
+                    eprintln!("catr: {}: {}", dest.path, error_message(&err));
+                    dest.file = None;
+                    self.ok = false;
+                }
+            }
+        }
+    }
+}
+
+fn is_input(path: &str, inputs: &[String]) -> bool {
+    match fs::metadata(path).ok().as_ref().and_then(file_id) {
+        Some(id) => inputs.iter().any(|input| output::is_output(input, &[id])),
+        None => false,
+    }
+}
+
+impl<W: Write> Write for Tee<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_out(|out| out.write_all(buf));
+        self.each(|file| file.write_all(buf));
+        self.check_lost()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_out(|out| out.flush());
+        self.each(|file| file.flush());
+        self.check_lost()
+    }
+}
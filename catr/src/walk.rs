@@ -0,0 +1,184 @@
+use crate::{error_message, Config, MyResult};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::{DirEntry, WalkBuilder};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// How much of a file to look at when deciding whether it is binary.
+const SNIFF_LEN: u64 = 8192;
+
+// The --include or --exclude globs. One with a slash is matched against
+// the path below the directory being walked, one without against the
+// file name alone, so "*.sql" matches at any depth.
+#[derive(Debug, Clone)]
+pub struct Globs {
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl Default for Globs {
+    fn default() -> Self {
+        Globs {
+            names: GlobSet::empty(),
+            paths: GlobSet::empty(),
+        }
+    }
+}
+
+impl Globs {
+    pub fn new<'a>(patterns: impl IntoIterator<Item = &'a str>) -> MyResult<Self> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|_| pattern)?;
+            match pattern.contains('/') {
+                true => paths.add(glob),
+                false => names.add(glob),
+            };
+        }
+        Ok(Globs {
+            names: names.build()?,
+            paths: paths.build()?,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.paths.is_empty()
+    }
+
+    fn matches(&self, rel: &Path) -> bool {
+        rel.file_name()
+            .is_some_and(|name| self.names.is_match(name))
+            || self.paths.is_match(rel)
+    }
+}
+
+// Like grep and git, a file is taken to be binary if a NUL byte shows up
+// near the start. A file that can't be read is left for cat to report.
+fn is_binary(path: &Path) -> bool {
+    let mut head = Vec::new();
+    match File::open(path).and_then(|file| file.take(SNIFF_LEN).read_to_end(&mut head)) {
+        Ok(_) => head.contains(&0),
+        Err(_) => false,
+    }
+}
+
+// The path the error is about, followed by the message the way
+// error_message gives it.
+fn walk_error(err: &ignore::Error) -> String {
+    match err {
+        ignore::Error::WithPath { path, err } => {
+            format!("{}: {}", path.display(), walk_error(err))
+        }
+        ignore::Error::WithDepth { err, .. } => walk_error(err),
+        _ => match err.io_error() {
+            Some(err) => error_message(err),
+            None => err.to_string(),
+        },
+    }
+}
+
+// The regular files below `dir` that pass the filters, sorted by name
+// at each level. Errors on the way are reported and skipped.
+fn walk(dir: &str, config: &Config, ok: &mut bool) -> Vec<String> {
+    let root = PathBuf::from(dir);
+    let excludes = config.excludes.clone();
+    let gitignore = config.gitignore;
+    let walker = WalkBuilder::new(dir)
+        .standard_filters(false)
+        .parents(gitignore)
+        .git_ignore(gitignore)
+        .git_exclude(gitignore)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry: &DirEntry| {
+            let rel = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+            entry.depth() == 0
+                || !(excludes.matches(rel) || gitignore && entry.file_name() == ".git")
+        })
+        .build();
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                eprintln!("catr: {}", walk_error(&err));
+                *ok = false;
+                continue;
+            }
+        };
+        if !entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+        if !config.includes.is_empty() && !config.includes.matches(rel) {
+            continue;
+        }
+        if !config.binary && is_binary(entry.path()) {
+            continue;
+        }
+        files.push(entry.path().to_string_lossy().into_owned());
+    }
+    files
+}
+
+// Replace each directory among the inputs with the files below it, for
+// --recursive. Returns false if part of a tree couldn't be read.
+pub fn expand(config: &mut Config) -> bool {
+    let mut ok = true;
+    let files = std::mem::take(&mut config.files);
+    let mut origins = std::mem::take(&mut config.origins).into_iter();
+    for filename in files {
+        let origin = origins.next().flatten();
+        if filename == "-" || !Path::new(&filename).is_dir() {
+            config.files.push(filename);
+            config.origins.push(origin);
+            continue;
+        }
+        for file in walk(&filename, config, &mut ok) {
+            config.files.push(file);
+            config.origins.push(origin.clone());
+        }
+    }
+    ok
+}
+
+#[test]
+fn test_globs() {
+    let globs = Globs::new(["*.sql", "vendor/**"]).unwrap();
+    assert!(globs.matches(Path::new("schema.sql")));
+    assert!(globs.matches(Path::new("db/migrations/001.sql")));
+    assert!(globs.matches(Path::new("vendor/lib/a.txt")));
+    assert!(!globs.matches(Path::new("src/vendor/a.txt")));
+    assert!(!globs.matches(Path::new("notes.sql.txt")));
+
+    // Without ** a star stays within one directory
+    let globs = Globs::new(["db/*.sql"]).unwrap();
+    assert!(globs.matches(Path::new("db/001.sql")));
+    assert!(!globs.matches(Path::new("db/old/001.sql")));
+
+    assert!(Globs::default().is_empty());
+
+    let res = Globs::new(["src/[a"]);
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "src/[a");
+}
+
+#[test]
+fn test_walk_error() {
+    let err = ignore::Error::WithDepth {
+        depth: 1,
+        err: Box::new(ignore::Error::WithPath {
+            path: PathBuf::from("logs/private"),
+            err: Box::new(ignore::Error::Io(std::io::Error::from_raw_os_error(13))),
+        }),
+    };
+    assert_eq!(walk_error(&err), "logs/private: Permission denied");
+}
@@ -1,8 +1,14 @@
-use assert_cmd::Command;
+use assert_cmd::{cargo::cargo_bin, Command};
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
 use std::error::Error;
-use std::fs;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 type TestResult = Result<(), Box<dyn Error>>;
 
@@ -11,6 +17,12 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const SPIDERS: &str = "tests/inputs/spiders.txt";
 const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+const CONTROL: &str = "tests/inputs/control.txt";
+const LATIN1: &str = "tests/inputs/latin1.txt";
+const BYTES: &str = "tests/inputs/bytes.bin";
+const BLANKS: &str = "tests/inputs/blanks.txt";
+const MIXED_EOL: &str = "tests/inputs/mixed-eol.sh";
+const BUSTLE_GZ: &str = "tests/inputs/the-bustle.txt.gz";
 
 // --------------------------------------------------
 #[test]
@@ -43,18 +55,47 @@ fn gen_bad_file() -> String {
 #[test]
 fn skips_bad_file() -> TestResult {
     let bad = gen_bad_file();
-    let expected = format!("{}: .* [(]os error 2[)]", bad);
+    let expected = format!("catr: {}: No such file or directory\n", bad);
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
-        .success()
-        .stderr(predicate::str::is_match(expected)?);
+        .failure()
+        .stderr(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_bad_file_between_good_files() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = fs::read("tests/expected/all.n.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-n", FOX, &bad, SPIDERS, BUSTLE])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(format!("catr: {}: No such file or directory\n", bad));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_directory() -> TestResult {
+    let expected = fs::read("tests/expected/all.out")?;
+    for flag in ["--squeeze-blank", "--no-decompress"] {
+        Command::cargo_bin(PRG)?
+            .args([flag, FOX, "tests/inputs", SPIDERS, BUSTLE])
+            .assert()
+            .failure()
+            .stdout(expected.clone())
+            .stderr("catr: tests/inputs: Is a directory\n");
+    }
     Ok(())
 }
 
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> TestResult {
-    let expected = fs::read_to_string(expected_file)?;
+    let expected = fs::read(expected_file)?;
     Command::cargo_bin(PRG)?
         .args(args)
         .assert()
@@ -69,8 +110,8 @@ fn run_stdin(
     args: &[&str],
     expected_file: &str,
 ) -> TestResult {
-    let input = fs::read_to_string(input_file)?;
-    let expected = fs::read_to_string(expected_file)?;
+    let input = fs::read(input_file)?;
+    let expected = fs::read(expected_file)?;
     Command::cargo_bin(PRG)?
         .args(args)
         .write_stdin(input)
@@ -198,3 +239,1515 @@ fn all_n() -> TestResult {
 fn all_b() -> TestResult {
     run(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn control_show_all() -> TestResult {
+    run(&["-A", CONTROL], "tests/expected/control.txt.A.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn control_show_all_long() -> TestResult {
+    run(&["--show-all", CONTROL], "tests/expected/control.txt.A.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn control_e() -> TestResult {
+    run(&["-e", CONTROL], "tests/expected/control.txt.e.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn control_t() -> TestResult {
+    run(&["-t", CONTROL], "tests/expected/control.txt.t.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn control_show_ends() -> TestResult {
+    run(&["--show-ends", CONTROL], "tests/expected/control.txt.E.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn control_show_tabs() -> TestResult {
+    run(&["--show-tabs", CONTROL], "tests/expected/control.txt.T.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn control_show_nonprinting() -> TestResult {
+    run(
+        &["--show-nonprinting", CONTROL],
+        "tests/expected/control.txt.v.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn control_n_show_all() -> TestResult {
+    run(&["-n", "-A", CONTROL], "tests/expected/control.txt.nA.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_show_all() -> TestResult {
+    run(&["-A", BUSTLE], "tests/expected/the-bustle.txt.A.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_b_show_ends() -> TestResult {
+    run(&["-b", "-E", BUSTLE], "tests/expected/the-bustle.txt.bE.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1() -> TestResult {
+    run(&[LATIN1], "tests/expected/latin1.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_stdin() -> TestResult {
+    run_stdin(LATIN1, &["-"], "tests/expected/latin1.txt.stdin.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_n() -> TestResult {
+    run(&["-n", LATIN1], "tests/expected/latin1.txt.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_b() -> TestResult {
+    run(&["-b", LATIN1], "tests/expected/latin1.txt.b.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_show_ends() -> TestResult {
+    run(&["-E", LATIN1], "tests/expected/latin1.txt.E.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_show_all() -> TestResult {
+    run(&["-A", LATIN1], "tests/expected/latin1.txt.A.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_n() -> TestResult {
+    run(&["-n", BYTES], "tests/expected/bytes.bin.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_show_nonprinting() -> TestResult {
+    run(&["-v", BYTES], "tests/expected/bytes.bin.v.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn blanks_s() -> TestResult {
+    run(&["-s", BLANKS], "tests/expected/blanks.txt.s.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn blanks_sn() -> TestResult {
+    run(&["-s", "-n", BLANKS], "tests/expected/blanks.txt.sn.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn blanks_sb() -> TestResult {
+    run(&["--squeeze-blank", "-b", BLANKS], "tests/expected/blanks.txt.sb.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_s() -> TestResult {
+    run(&["-s", BUSTLE], "tests/expected/the-bustle.txt.s.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_sn() -> TestResult {
+    run(&["-s", "-n", BUSTLE], "tests/expected/the-bustle.txt.sn.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_sb() -> TestResult {
+    run(&["--squeeze-blank", "-b", BUSTLE], "tests/expected/the-bustle.txt.sb.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn spiders_s() -> TestResult {
+    run(&["-s", SPIDERS], "tests/expected/spiders.txt.s.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn spiders_sn() -> TestResult {
+    run(&["-s", "-n", SPIDERS], "tests/expected/spiders.txt.sn.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn spiders_sb() -> TestResult {
+    run(&["--squeeze-blank", "-b", SPIDERS], "tests/expected/spiders.txt.sb.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn all_n_per_file() -> TestResult {
+    run(
+        &["-n", "--number-per-file", FOX, SPIDERS, BUSTLE],
+        "tests/expected/all.n.per-file.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_b_per_file() -> TestResult {
+    run(
+        &["-b", "--number-per-file", FOX, SPIDERS, BUSTLE],
+        "tests/expected/all.b.per-file.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_n_start_width_separator() -> TestResult {
+    run(
+        &[
+            "-n",
+            "--number-start",
+            "500",
+            "--number-width",
+            "4",
+            "--number-separator",
+            ": ",
+            FOX,
+            SPIDERS,
+            BUSTLE,
+        ],
+        "tests/expected/all.n.start500.w4.sep.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn unterminated_line_continues_numbering() -> TestResult {
+    run(&["-n", LATIN1, FOX], "tests/expected/latin1.fox.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_number_start() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--number-start", "foo", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal number start -- foo"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_number_width() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--number-width", "0", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal number width -- 0"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "gzip")]
+fn gzip() -> TestResult {
+    run(&[BUSTLE_GZ], "tests/expected/the-bustle.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "gzip")]
+fn gzip_n() -> TestResult {
+    run(&["-n", BUSTLE_GZ], "tests/expected/the-bustle.txt.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "gzip")]
+fn gzip_stdin_b() -> TestResult {
+    run_stdin(
+        BUSTLE_GZ,
+        &["-b", "-"],
+        "tests/expected/the-bustle.txt.b.stdin.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "gzip")]
+fn gzip_sniffed_without_extension() -> TestResult {
+    run(
+        &["tests/inputs/spiders.dat"],
+        "tests/expected/spiders.txt.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "gzip")]
+fn gzip_multiple_members() -> TestResult {
+    run(
+        &["tests/inputs/fox-spiders.gz"],
+        "tests/expected/fox-spiders.gz.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn gzip_no_decompress() -> TestResult {
+    run(&["--no-decompress", BUSTLE_GZ], BUSTLE_GZ)
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "bzip2")]
+fn bzip2() -> TestResult {
+    run(
+        &["-n", "tests/inputs/the-bustle.txt.bz2"],
+        "tests/expected/the-bustle.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bzip2_lookalike_text_is_copied() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .write_stdin("BZhello\n")
+        .assert()
+        .success()
+        .stdout("BZhello\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "xz")]
+fn xz() -> TestResult {
+    run(
+        &["-n", "tests/inputs/the-bustle.txt.xz"],
+        "tests/expected/the-bustle.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "zstd")]
+fn zstd() -> TestResult {
+    run(
+        &["-n", "tests/inputs/the-bustle.txt.zst"],
+        "tests/expected/the-bustle.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "xz")]
+fn xz_magic_split_across_reads() -> TestResult {
+    // The pipe delivers the first bytes of the magic number on their own.
+    let input = fs::read("tests/inputs/the-bustle.txt.xz")?;
+    for (args, expected) in [
+        (&[][..], "tests/expected/the-bustle.txt.out"),
+        (&["-n"], "tests/expected/the-bustle.txt.n.out"),
+    ] {
+        let mut child = std::process::Command::new(cargo_bin(PRG))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(&input[..3])?;
+        stdin.flush()?;
+        thread::sleep(Duration::from_millis(300));
+        stdin.write_all(&input[3..])?;
+        drop(stdin);
+        let output = child.wait_with_output()?;
+        assert!(output.status.success());
+        assert_eq!(output.stdout, fs::read(expected)?);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(all(feature = "gzip", feature = "zstd"))]
+fn compressed_inputs_concatenate() -> TestResult {
+    let expected = [
+        fs::read("tests/expected/the-bustle.txt.out")?,
+        fs::read("tests/expected/spiders.txt.out")?,
+    ]
+    .concat();
+    Command::cargo_bin(PRG)?
+        .args([
+            "tests/inputs/the-bustle.txt.zst",
+            "tests/inputs/spiders.dat",
+        ])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+// A running `catr --follow` whose stdout is collected in the background.
+struct Follower {
+    child: Child,
+    output: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Follower {
+    fn spawn(args: &[&str], path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut child = std::process::Command::new(cargo_bin(PRG))
+            .args(args)
+            .arg("--follow")
+            .arg(path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let mut stdout = child.stdout.take().unwrap();
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&output);
+        thread::spawn(move || {
+            let mut buf = [0; 1024];
+            while let Ok(n) = stdout.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                sink.lock().unwrap().extend_from_slice(&buf[..n]);
+            }
+        });
+        Ok(Follower { child, output })
+    }
+
+    // Wait until the output is exactly `expected`, failing after a while.
+    fn expect(&self, expected: &str) -> TestResult {
+        let start = Instant::now();
+        loop {
+            let output = self.output.lock().unwrap().clone();
+            if output == expected.as_bytes() {
+                return Ok(());
+            }
+            if start.elapsed() > Duration::from_secs(10) {
+                return Err(format!(
+                    "expected {:?}, got {:?}",
+                    expected,
+                    String::from_utf8_lossy(&output)
+                )
+                .into());
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+impl Drop for Follower {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// --------------------------------------------------
+fn append(path: &Path, text: &str) -> TestResult {
+    let mut file = OpenOptions::new().append(true).open(path)?;
+    file.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_appended_data() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("app.log");
+    fs::write(&log, "one\n")?;
+    let follower = Follower::spawn(&[], &log)?;
+    follower.expect("one\n")?;
+    append(&log, "two\nthr")?;
+    follower.expect("one\ntwo\nthr")?;
+    append(&log, "ee\n")?;
+    follower.expect("one\ntwo\nthree\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_n_continues_numbering() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("app.log");
+    fs::write(&log, "one\n")?;
+    let follower = Follower::spawn(&["-n", FOX], &log)?;
+    follower.expect(
+        "     1\tThe quick brown fox jumps over the lazy dog.\n     2\tone\n",
+    )?;
+    append(&log, "two\n\nthree\n")?;
+    follower.expect(
+        "     1\tThe quick brown fox jumps over the lazy dog.\n     2\tone\n     \
+         3\ttwo\n     4\t\n     5\tthree\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_b_partial_line() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("app.log");
+    fs::write(&log, "one\n\ntw")?;
+    let follower = Follower::spawn(&["-b"], &log)?;
+    follower.expect("     1\tone\n\n     2\ttw")?;
+    append(&log, "o\nthree\n")?;
+    follower.expect("     1\tone\n\n     2\ttwo\n     3\tthree\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_truncated_file() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("app.log");
+    fs::write(&log, "one\ntwo\n")?;
+    let follower = Follower::spawn(&["-n"], &log)?;
+    follower.expect("     1\tone\n     2\ttwo\n")?;
+    fs::write(&log, "")?;
+    // Give catr a chance to notice the truncation before new data lands.
+    thread::sleep(Duration::from_secs(1));
+    append(&log, "three\n")?;
+    follower.expect("     1\tone\n     2\ttwo\n     3\tthree\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_rotated_file() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("app.log");
+    fs::write(&log, "one\n")?;
+    let follower = Follower::spawn(&[], &log)?;
+    follower.expect("one\n")?;
+    let rotated = dir.path().join("app.log.1");
+    fs::rename(&log, &rotated)?;
+    append(&rotated, "two\n")?;
+    fs::write(&log, "three\n")?;
+    follower.expect("one\ntwo\nthree\n")?;
+    append(&log, "four\n")?;
+    follower.expect("one\ntwo\nthree\nfour\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_follow_from_encoding() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "--from-encoding", "utf-16le", BUSTLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "gzip")]
+fn dies_follow_compressed() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", BUSTLE_GZ])
+        .timeout(Duration::from_secs(10))
+        .assert()
+        .failure()
+        .stderr(format!(
+            "catr: {}: cannot follow compressed input\n",
+            BUSTLE_GZ
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_range_n() -> TestResult {
+    run(
+        &["-n", "--range", "3:6", BUSTLE],
+        "tests/expected/the-bustle.txt.range3-6.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_range_count_b() -> TestResult {
+    run(
+        &["-b", "--range", "4:+3", BUSTLE],
+        "tests/expected/the-bustle.txt.range4+3.b.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_range_from_end_n() -> TestResult {
+    run(
+        &["-n", "--range", "-2:", FOX, SPIDERS, BUSTLE],
+        "tests/expected/all.range-2.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn range_from_end_stdin() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--range", "-3:-2,1", "-"])
+        .write_stdin("a\nb\nc\nd\ne")
+        .assert()
+        .success()
+        .stdout("a\nc\nd\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn range_from_end_pipe() -> TestResult {
+    // /dev/stdin is the pipe itself, which can't be read twice
+    Command::cargo_bin(PRG)?
+        .args(["--range", "-3:-2,1", "/dev/stdin"])
+        .write_stdin("a\nb\nc\nd\ne")
+        .assert()
+        .success()
+        .stdout("a\nc\nd\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn range_stops_reading_early() -> TestResult {
+    // stdin is left open, so catr only exits if it stops after line 2.
+    let mut child = std::process::Command::new(cargo_bin(PRG))
+        .args(["--lines", "1:2"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"one\ntwo\nthree\n")?;
+    let start = Instant::now();
+    while child.try_wait()?.is_none() {
+        if start.elapsed() > Duration::from_secs(10) {
+            child.kill()?;
+            return Err("catr kept reading after the last range".into());
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    let output = child.wait_with_output()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"one\ntwo\n");
+    drop(stdin);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_range() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--range", "5:foo", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal range -- 5:foo"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn utf16le_bom_auto() -> TestResult {
+    run(
+        &[
+            "--from-encoding",
+            "auto",
+            "tests/inputs/the-bustle.utf16le.txt",
+        ],
+        "tests/expected/the-bustle.utf16le.txt.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn utf16le_bom_auto_stdin() -> TestResult {
+    run_stdin(
+        "tests/inputs/the-bustle.utf16le.txt",
+        &["--from-encoding", "auto"],
+        "tests/expected/the-bustle.utf16le.txt.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn utf16be_n() -> TestResult {
+    run(
+        &[
+            "-n",
+            "--from-encoding",
+            "UTF-16BE",
+            "tests/inputs/the-bustle.utf16be.txt",
+        ],
+        "tests/expected/the-bustle.utf16be.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn windows_1252() -> TestResult {
+    run(
+        &[
+            "--from-encoding",
+            "windows-1252",
+            "tests/inputs/windows-1252.txt",
+        ],
+        "tests/expected/windows-1252.txt.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_from_latin1_v() -> TestResult {
+    run(
+        &["-v", "--from-encoding", "latin1", LATIN1],
+        "tests/expected/latin1.txt.latin1.v.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn auto_without_bom_is_raw() -> TestResult {
+    run(
+        &["--from-encoding", "auto", LATIN1],
+        "tests/expected/latin1.txt.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn utf16_is_raw_by_default() -> TestResult {
+    run(
+        &["tests/inputs/the-bustle.utf16le.txt"],
+        "tests/inputs/the-bustle.utf16le.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_encoding() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--from-encoding", "ebcdic", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown encoding -- ebcdic"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "pretty")]
+fn pretty_falls_back_when_piped() -> TestResult {
+    run(
+        &["--pretty", "-n", FOX, SPIDERS, BUSTLE],
+        "tests/expected/all.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(feature = "pretty")]
+fn dies_pretty_with_plain_only_flags() -> TestResult {
+    for flag in ["-s", "-E", "-T", "-v", "--range=1:2", "--eol=lf"] {
+        Command::cargo_bin(PRG)?
+            .args(["--pretty", flag, FOX])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_hex_with_line_flags() -> TestResult {
+    for flag in [
+        "-n",
+        "-b",
+        "-s",
+        "-E",
+        "-T",
+        "-v",
+        "-A",
+        "--eol=lf",
+        "--ensure-final-newline",
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(["--hex", flag, FOX])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn hex() -> TestResult {
+    run(&["--hex", BYTES], "tests/expected/bytes.bin.hex.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_squeezes_repeated_lines() -> TestResult {
+    run(
+        &["--hex", "tests/inputs/zeros.bin"],
+        "tests/expected/zeros.bin.hex.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_multiple_files_stdin() -> TestResult {
+    run_stdin(
+        SPIDERS,
+        &["--hex", FOX, "-"],
+        "tests/expected/fox-spiders.hex.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_plain() -> TestResult {
+    run(
+        &["--hex", "--plain", BYTES],
+        "tests/expected/bytes.bin.hex-plain.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_shows_compressed_bytes() -> TestResult {
+    run(
+        &["--hex", "--plain", BUSTLE_GZ],
+        "tests/expected/the-bustle.txt.gz.hex-plain.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_reverse() -> TestResult {
+    run(
+        &[
+            "--hex",
+            "--reverse",
+            "tests/expected/bytes.bin.hex.out",
+            "tests/expected/zeros.bin.hex.out",
+        ],
+        "tests/expected/bytes-zeros.bin.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_plain_reverse_stdin() -> TestResult {
+    run_stdin(
+        "tests/expected/bytes.bin.hex-plain.out",
+        &["--hex", "--plain", "--reverse"],
+        BYTES,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_hex_dump() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--hex", "--reverse", FOX])
+        .assert()
+        .failure()
+        .stderr(format!("catr: {}: line 1: not a hexdump -C line\n", FOX));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn eol_keep() -> TestResult {
+    run(&["--eol", "keep", MIXED_EOL], MIXED_EOL)
+}
+
+// --------------------------------------------------
+#[test]
+fn eol_lf() -> TestResult {
+    run(
+        &["--eol", "lf", MIXED_EOL],
+        "tests/expected/mixed-eol.sh.lf.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn eol_crlf_stdin() -> TestResult {
+    run_stdin(
+        MIXED_EOL,
+        &["--eol=crlf"],
+        "tests/expected/mixed-eol.sh.crlf.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn eol_lf_ensure_final_newline() -> TestResult {
+    run(
+        &[
+            "--eol",
+            "lf",
+            "--ensure-final-newline",
+            MIXED_EOL,
+            MIXED_EOL,
+        ],
+        "tests/expected/mixed-eol.sh.lf.final.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn eol_crlf_ensure_final_newline_n_show_ends() -> TestResult {
+    run(
+        &[
+            "--eol",
+            "crlf",
+            "--ensure-final-newline",
+            "-n",
+            "-E",
+            MIXED_EOL,
+        ],
+        "tests/expected/mixed-eol.sh.crlf.final.nE.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn ensure_final_newline_leaves_complete_files() -> TestResult {
+    run(
+        &["--ensure-final-newline", FOX, SPIDERS, BUSTLE],
+        "tests/expected/all.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_eol() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--eol", "cr", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("isn't a valid value"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn prefetch_all_n() -> TestResult {
+    run(
+        &["--prefetch", "2", "-n", FOX, SPIDERS, BUSTLE],
+        "tests/expected/all.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn prefetch_far_ahead() -> TestResult {
+    run(
+        &["--prefetch", "100000", "-n", FOX, SPIDERS, BUSTLE],
+        "tests/expected/all.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn prefetch_range_from_end_n() -> TestResult {
+    run(
+        &[
+            "--prefetch",
+            "4",
+            "-n",
+            "--range",
+            "-2:",
+            FOX,
+            SPIDERS,
+            BUSTLE,
+        ],
+        "tests/expected/all.range-2.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn prefetch_skips_bad_file() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = fs::read("tests/expected/all.n.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--prefetch", "3", "-n", FOX, &bad, SPIDERS, BUSTLE])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(format!("catr: {}: No such file or directory\n", bad));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn prefetch_matches_sequential() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let mut files = Vec::new();
+    for i in 0..200 {
+        let path = dir.path().join(format!("{:03}.txt", i));
+        // Some files end without a newline, so lines run on across files
+        let text = match i % 3 {
+            0 => format!("file {}\n\n\nend\n", i),
+            1 => format!("file {}\npartial", i),
+            _ => String::new(),
+        };
+        fs::write(&path, text)?;
+        files.push(path.to_string_lossy().into_owned());
+    }
+    files.insert(100, "-".to_string());
+    for opts in [&[][..], &["-b", "-s"], &["-n", "--range", "-1:"]] {
+        let args: Vec<&str> = opts
+            .iter()
+            .copied()
+            .chain(files.iter().map(|f| &f[..]))
+            .collect();
+        let sequential = Command::cargo_bin(PRG)?
+            .args(&args)
+            .write_stdin("from stdin\n")
+            .output()?;
+        Command::cargo_bin(PRG)?
+            .args(["--prefetch", "8"])
+            .args(&args)
+            .write_stdin("from stdin\n")
+            .assert()
+            .success()
+            .stdout(sequential.stdout);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_prefetch() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--prefetch", "lots", FOX])
+        .assert()
+        .failure()
+        .stderr("illegal prefetch count -- lots\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_includes_itself() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let out = dir.path().join("all.txt");
+    fs::copy(FOX, &out)?;
+    let out = out.to_string_lossy().into_owned();
+    Command::cargo_bin(PRG)?
+        .args(["-n", &out, SPIDERS, BUSTLE, "-o", &out])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(fs::read(&out)?, fs::read("tests/expected/all.n.out")?);
+    // Nothing is left behind but the output
+    assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn output_keeps_going_after_bad_file() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let out = dir.path().join("out.txt");
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args([FOX, &bad, SPIDERS, "--output", &out.to_string_lossy()])
+        .assert()
+        .failure()
+        .stderr(format!("catr: {}: No such file or directory\n", bad));
+    let mut expected = fs::read(FOX)?;
+    expected.extend(fs::read(SPIDERS)?);
+    assert_eq!(fs::read(&out)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_output_in_missing_dir() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let out = dir.path().join("no-such-dir").join("out.txt");
+    let out = out.to_string_lossy().into_owned();
+    Command::cargo_bin(PRG)?
+        .args([FOX, "-o", &out])
+        .assert()
+        .failure()
+        .stderr(format!("{}: No such file or directory\n", out));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn refuses_input_that_is_stdout() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let out = dir.path().join("out.txt");
+    fs::copy(FOX, &out)?;
+    let file = OpenOptions::new().append(true).open(&out)?;
+    let output = std::process::Command::new(cargo_bin(PRG))
+        .arg(SPIDERS)
+        .arg(&out)
+        .stdout(file)
+        .output()?;
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr)?,
+        format!("catr: {}: input file is output file\n", out.display())
+    );
+    let mut expected = fs::read(FOX)?;
+    expected.extend(fs::read(SPIDERS)?);
+    assert_eq!(fs::read(&out)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn tee_overwrite_and_append() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let copy = dir.path().join("copy.txt");
+    let log = dir.path().join("log.txt");
+    fs::write(&copy, "old\n")?;
+    fs::write(&log, "old\n")?;
+    let expected = fs::read("tests/expected/all.n.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-n", FOX, SPIDERS, BUSTLE])
+        .args(["--tee", &copy.to_string_lossy()])
+        .args(["--tee-append", &log.to_string_lossy()])
+        .assert()
+        .success()
+        .stdout(expected.clone());
+    assert_eq!(fs::read(&copy)?, expected);
+    assert_eq!(fs::read(&log)?, [&b"old\n"[..], &expected].concat());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn tee_with_output() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let out = dir.path().join("out.txt");
+    let copy = dir.path().join("copy.txt");
+    Command::cargo_bin(PRG)?
+        .args([FOX, "-o", &out.to_string_lossy()])
+        .args(["--tee", &copy.to_string_lossy()])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(fs::read(&out)?, fs::read(FOX)?);
+    assert_eq!(fs::read(&copy)?, fs::read(FOX)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn tee_bad_target_keeps_others() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let copy = dir.path().join("copy.txt");
+    let bad = dir.path().to_string_lossy().into_owned();
+    Command::cargo_bin(PRG)?
+        .args([FOX, "--tee", &bad, "--tee", &copy.to_string_lossy()])
+        .assert()
+        .failure()
+        .stdout(fs::read(FOX)?)
+        .stderr(format!("catr: {}: Is a directory\n", bad));
+    assert_eq!(fs::read(&copy)?, fs::read(FOX)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn write_error_stops_run() -> TestResult {
+    for args in [vec![FOX, "nope", SPIDERS], vec!["-n", FOX, "nope", SPIDERS]] {
+        let output = std::process::Command::new(cargo_bin(PRG))
+            .args(&args)
+            .stdout(fs::File::create("/dev/full")?)
+            .output()?;
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(
+            String::from_utf8(output.stderr)?,
+            "catr: write error: No space left on device\n"
+        );
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn tee_write_failure_keeps_others() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let copy = dir.path().join("copy.txt");
+    Command::cargo_bin(PRG)?
+        .args([FOX, SPIDERS, "--tee", "/dev/full"])
+        .args(["--tee", &copy.to_string_lossy()])
+        .assert()
+        .failure()
+        .stdout([fs::read(FOX)?, fs::read(SPIDERS)?].concat())
+        .stderr("catr: /dev/full: No space left on device\n");
+    assert_eq!(
+        fs::read(&copy)?,
+        [fs::read(FOX)?, fs::read(SPIDERS)?].concat()
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn tee_keeps_going_when_stdout_fails() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let copy = dir.path().join("copy.txt");
+    let output = std::process::Command::new(cargo_bin(PRG))
+        .args([FOX, BUSTLE, "--tee", &copy.to_string_lossy()])
+        .stdout(fs::File::create("/dev/full")?)
+        .output()?;
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr)?,
+        "catr: write error: No space left on device\n"
+    );
+    assert_eq!(
+        fs::read(&copy)?,
+        [fs::read(FOX)?, fs::read(BUSTLE)?].concat()
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn refuses_input_that_is_tee_target() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log.txt");
+    fs::copy(FOX, &log)?;
+    let log = log.to_string_lossy().into_owned();
+    Command::cargo_bin(PRG)?
+        .args([SPIDERS, &log, "--tee-append", &log])
+        .assert()
+        .failure()
+        .stdout(fs::read(SPIDERS)?)
+        .stderr(format!("catr: {}: input file is output file\n", log));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn refuses_tee_target_that_is_input() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("log.txt");
+    fs::copy(FOX, &log)?;
+    let log = log.to_string_lossy().into_owned();
+    let mut expected = fs::read(SPIDERS)?;
+    expected.extend(fs::read(FOX)?);
+    Command::cargo_bin(PRG)?
+        .args([SPIDERS, &log, "--tee", &log])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(format!("catr: {}: input file is output file\n", log));
+    assert_eq!(fs::read(&log)?, fs::read(FOX)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn headers_match_head() -> TestResult {
+    run(
+        &["--headers", FOX, SPIDERS, BUSTLE],
+        "tests/expected/all.headers.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn headers_n_per_file_separator() -> TestResult {
+    run(
+        &[
+            "--headers",
+            "-n",
+            "--number-per-file",
+            "--separator",
+            "----\n",
+            FOX,
+            SPIDERS,
+            BUSTLE,
+        ],
+        "tests/expected/all.headers.n.per-file.sep.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn headers_after_separator_without_newline() -> TestResult {
+    let expected = format!(
+        "==> {} <==\n{}--\n==> {} <==\n{}",
+        FOX,
+        fs::read_to_string(FOX)?,
+        SPIDERS,
+        fs::read_to_string(SPIDERS)?
+    );
+    Command::cargo_bin(PRG)?
+        .args(["--headers", "--separator=--", FOX, SPIDERS])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn headers_skip_bad_file() -> TestResult {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--headers", &bad, FOX, SPIDERS, BUSTLE])
+        .assert()
+        .failure()
+        .stdout(fs::read("tests/expected/all.headers.out")?)
+        .stderr(format!("catr: {}: No such file or directory\n", bad));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn headers_after_unterminated_line() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--headers", "-", FOX])
+        .write_stdin("no newline")
+        .assert()
+        .success()
+        .stdout(
+            "==> - <==\nno newline\n\n==> tests/inputs/fox.txt <==\n\
+             The quick brown fox jumps over the lazy dog.\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn null_separated() -> TestResult {
+    run(
+        &["--null-separated", FOX, EMPTY, SPIDERS],
+        "tests/expected/fox-empty-spiders.null.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_separator_and_null_separated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--separator", "--", "--null-separated", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files_from_reports_manifest_line() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let list = dir.path().join("list.txt");
+    let bad = gen_bad_file();
+    fs::write(
+        &list,
+        format!("{}\n{}\n{}\n{}\n", FOX, bad, SPIDERS, BUSTLE),
+    )?;
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--files-from", &list.to_string_lossy()])
+        .assert()
+        .failure()
+        .stdout(fs::read("tests/expected/all.n.out")?)
+        .stderr(format!(
+            "catr: {}:2: {}: No such file or directory\n",
+            list.display(),
+            bad
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_stdin_after_file_args() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", FOX, "--files0-from", "-"])
+        .write_stdin(format!("{}\0{}\0", SPIDERS, BUSTLE))
+        .assert()
+        .success()
+        .stdout(fs::read("tests/expected/all.n.out")?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files_from_many_files() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let mut list = String::new();
+    let mut expected = String::new();
+    for i in 0..3000 {
+        let path = dir.path().join(format!("{}.txt", i));
+        fs::write(&path, format!("{}\n", i))?;
+        list.push_str(&format!("{}\n", path.display()));
+        expected.push_str(&format!("{}\n", i));
+    }
+    Command::cargo_bin(PRG)?
+        .args(["--files-from", "-"])
+        .write_stdin(list)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_empty_manifest_entry() -> TestResult {
+    let expected = [
+        fs::read("tests/expected/fox.txt.out")?,
+        fs::read("tests/expected/spiders.txt.out")?,
+    ]
+    .concat();
+    Command::cargo_bin(PRG)?
+        .args(["--files-from", "-"])
+        .write_stdin(format!("{}\n\n{}\n", FOX, SPIDERS))
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr("catr: -:2: invalid zero-length file name\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_missing_manifest() -> TestResult {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", &bad])
+        .assert()
+        .failure()
+        .stderr(format!("{}: No such file or directory\n", bad));
+    Ok(())
+}
+
+// --------------------------------------------------
+fn make_tree() -> Result<tempfile::TempDir, Box<dyn Error>> {
+    let dir = tempfile::tempdir()?;
+    for (path, text) in [
+        ("b.sql", "b\n"),
+        ("a.sql", "a\n"),
+        ("blob.sql", "bin\0ary\n"),
+        ("db/old/000.sql", "old\n"),
+        ("db/001.sql", "create\n"),
+        ("src/main.rs", "main\n"),
+        ("src/gen.sql", "generated\n"),
+        ("vendor/lib/x.sql", "vendored\n"),
+        (".gitignore", "gen.sql\n"),
+    ] {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, text)?;
+    }
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_include_exclude() -> TestResult {
+    let dir = make_tree()?;
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args(["-r", ".", "--include", "*.sql", "--exclude", "vendor/**"])
+        .arg("--headers")
+        .assert()
+        .success()
+        .stdout(
+            "==> ./a.sql <==\na\n\n==> ./b.sql <==\nb\n\n\
+             ==> ./db/001.sql <==\ncreate\n\n\
+             ==> ./db/old/000.sql <==\nold\n\n\
+             ==> ./src/gen.sql <==\ngenerated\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_gitignore_binary() -> TestResult {
+    let dir = make_tree()?;
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args(["-r", "db", "src", "--gitignore", "--binary", "b.sql"])
+        .args(["--exclude", "old", "blob.sql"])
+        .assert()
+        .success()
+        .stdout("create\nmain\nb\nbin\0ary\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_n_skips_binary() -> TestResult {
+    let dir = make_tree()?;
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args(["-n", "-r", ".", "--exclude", "*.rs", "--exclude", "db"])
+        .assert()
+        .success()
+        .stdout(
+            "     1\tgen.sql\n     2\ta\n     3\tb\n     4\tgenerated\n     \
+             5\tvendored\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_directory_without_recursive() -> TestResult {
+    let dir = make_tree()?;
+    Command::cargo_bin(PRG)?
+        .current_dir(dir.path())
+        .args(["a.sql", "db", "b.sql"])
+        .assert()
+        .failure()
+        .stdout("a\nb\n")
+        .stderr("catr: db: Is a directory\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_glob() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-r", ".", "--include", "src/[a"])
+        .assert()
+        .failure()
+        .stderr("illegal glob -- src/[a\n");
+    Ok(())
+}
@@ -0,0 +1,5 @@
+#!/bin/sh
+echo one
+echo two
+
+echo three
\ No newline at end of file
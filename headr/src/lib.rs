@@ -1,4 +1,6 @@
-use std::io::Read;
+use std::collections::VecDeque;
+use std::num::IntErrorKind;
+use std::io::Write;
 use std::{error::Error, io::{BufRead, BufReader, self}, fs::File};
 use clap::{Command, Arg};
 
@@ -7,8 +9,20 @@ type MyResult<T> = Result<T, Box<dyn Error>>;
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
-    lines: usize,
-    bytes: Option<usize>,
+    lines: Count,
+    bytes: Option<Count>,
+    chars: Option<usize>,
+    quiet: bool,
+    verbose: bool,
+    zero_terminated: bool,
+}
+
+// A -n or -c count. A leading '-' means everything except the last N,
+// as in GNU head.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Count {
+    First(usize),
+    AllBut(usize),
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -30,6 +44,7 @@ pub fn get_args() -> MyResult<Config> {
         .help("Number of lines")
         .short('n')
         .long("lines")
+        .allow_hyphen_values(true)
         .default_value("10"),
     )
     .arg(
@@ -38,28 +53,68 @@ pub fn get_args() -> MyResult<Config> {
         .help("Number of bytes")
         .short('c')
         .takes_value(true)
+        .allow_hyphen_values(true)
         .conflicts_with("lines")
         .long("bytes"),
     )
+    .arg(
+        Arg::new("chars")
+        .value_name("CHARS")
+        .help("Number of characters")
+        .long("chars")
+        .takes_value(true)
+        .conflicts_with_all(&["lines", "bytes"]),
+    )
+    .arg(
+        Arg::new("quiet")
+        .help("Never print headers giving file names")
+        .short('q')
+        .long("quiet")
+        .alias("silent")
+        .overrides_with("verbose"),
+    )
+    .arg(
+        Arg::new("verbose")
+        .help("Always print headers giving file names")
+        .short('v')
+        .long("verbose")
+        .overrides_with("quiet"),
+    )
+    .arg(
+        Arg::new("zero_terminated")
+        .help("Line delimiter is NUL, not newline")
+        .short('z')
+        .long("zero-terminated"),
+    )
     .get_matches();
 
 
     let lines = matches
     .value_of("lines")
-    .map(parse_positive_int)
+    .map(parse_count)
     .transpose()
     .map_err(|e| format!("illegal line count -- {}", e))?;
 
     let bytes = matches
     .value_of("bytes")
-    .map(parse_positive_int)
+    .map(parse_count)
     .transpose()
     .map_err(|e| format!("illegal byte count -- {}", e))?;
 
+    let chars = matches
+    .value_of("chars")
+    .map(parse_positive_int)
+    .transpose()
+    .map_err(|e| format!("illegal character count -- {}", e))?;
+
     Ok(Config {
         files: matches.values_of_lossy("files").unwrap(),
         lines: lines.unwrap(),
-        bytes
+        bytes,
+        chars,
+        quiet: matches.is_present("quiet"),
+        verbose: matches.is_present("verbose"),
+        zero_terminated: matches.is_present("zero_terminated"),
     })
 }
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
@@ -70,42 +125,191 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
 }
 
 pub fn run(config: Config) -> MyResult<()> {
+    let headers = config.verbose || (!config.quiet && config.files.len() > 1);
+    let delimiter = if config.zero_terminated { b'\0' } else { b'\n' };
     for (file_num,filename) in config.files.iter().enumerate() {
-        match open(&filename) {
+        match open(filename) {
             Err(e) => eprintln!("{}: {}", filename, e),
             Ok(mut file) => {
-                if config.files.len() > 1 {
+                if headers {
                         println!("{}==> {} <==", if file_num > 0 {"\n"} else {""}, filename);
                 }
-                if let Some(num_bytes) = config.bytes {
-                    let mut buffer = vec!(0; num_bytes);
-                    let mut handle = file.take(num_bytes as u64);
-                    let read = handle.read(&mut buffer)?;
-                    let s = String::from_utf8_lossy(&buffer[..read]);
-                    print!("{}", s);
-                } else {
-                    let mut line = String::new();
-                    // while let Ok(n) = file.read_line(&mut line) {
-                    //     if n == 0 { break; } // eof
-                    //     print!("{}", line);
-                    //     line.clear();
-                    // }
-                    for _ in 0..config.lines {
-                        let bytes = file.read_line(&mut line)?;
-                        if bytes == 0 {break;}  // eof
-                        print!("{}", line);
-                        line.clear() // otherwise the data will accumulate in your buffer
-                    }
-                    }
+                if let Some(num_chars) = config.chars {
+                    print_chars(file, num_chars)?;
+                    continue;
+                }
+                match config.bytes {
+                    Some(Count::AllBut(num_bytes)) => print_all_but_bytes(file, num_bytes)?,
+                    Some(Count::First(num_bytes)) => print_bytes(file, num_bytes)?,
+                    None => match config.lines {
+                        Count::AllBut(num_lines) => print_all_but_lines(file, num_lines, delimiter)?,
+                        Count::First(num_lines) => {
+                            let mut line = Vec::new();
+                            for _ in 0..num_lines {
+                                let bytes = file.read_until(delimiter, &mut line)?;
+                                if bytes == 0 {break;}  // eof
+                                io::stdout().write_all(&line)?;
+                                line.clear() // otherwise the data will accumulate in your buffer
+                            }
+                        }
+                    },
+                }
+        }
+    }
+}
+    Ok(())
+}
+
+// Print the first `num` bytes. A single read can come back short on a
+// pipe, so copy in chunks until the count is reached or the input ends.
+fn print_bytes(file: impl BufRead, num: usize) -> MyResult<()> {
+    let mut out = io::stdout().lock();
+    io::copy(&mut file.take(num as u64), &mut out)?;
+    Ok(())
+}
+
+// Print all but the last `num` lines, each ending in `delimiter`. Only the
+// most recent `num` lines are held back, so memory stays bounded however
+// long the input is.
+fn print_all_but_lines(mut file: impl BufRead, num: usize, delimiter: u8) -> MyResult<()> {
+    let mut out = io::stdout().lock();
+    let mut held: VecDeque<Vec<u8>> = VecDeque::new();
+    loop {
+        let mut line = Vec::new();
+        if file.read_until(delimiter, &mut line)? == 0 {
+            break; // eof
+        }
+        held.push_back(line);
+        if held.len() > num {
+            out.write_all(&held.pop_front().unwrap())?;
         }
     }
+    Ok(())
 }
+
+// Print all but the last `num` bytes, holding back only that many.
+fn print_all_but_bytes(mut file: impl BufRead, num: usize) -> MyResult<()> {
+    let mut out = io::stdout().lock();
+    let mut held: VecDeque<u8> = VecDeque::new();
+    loop {
+        let buf = file.fill_buf()?;
+        if buf.is_empty() {
+            break; // eof
+        }
+        held.extend(buf);
+        let len = buf.len();
+        file.consume(len);
+        if held.len() > num {
+            let excess = held.len() - num;
+            let (front, back) = held.as_slices();
+            let from_front = excess.min(front.len());
+            out.write_all(&front[..from_front])?;
+            out.write_all(&back[..excess - from_front])?;
+            held.drain(..excess);
+        }
+    }
+    Ok(())
+}
+
+// Print the first `num` characters, reading UTF-8 a buffer at a time and
+// carrying a character cut by the end of one buffer over to the next. A
+// byte that isn't valid UTF-8 counts as one character and is passed
+// through as it is.
+fn print_chars(mut file: impl BufRead, num: usize) -> MyResult<()> {
+    let mut out = io::stdout().lock();
+    let mut data = Vec::new();
+    let mut remaining = num;
+    while remaining > 0 {
+        let buf = file.fill_buf()?;
+        let eof = buf.is_empty();
+        data.extend_from_slice(buf);
+        let len = buf.len();
+        file.consume(len);
+        let mut pos = 0;
+        while remaining > 0 && pos < data.len() {
+            match char_len(&data[pos..]) {
+                Some(n) => pos += n,
+                None if eof => pos = data.len(), // truncated at eof
+                None => break,
+            }
+            remaining -= 1;
+        }
+        out.write_all(&data[..pos])?;
+        data.drain(..pos);
+        if eof {
+            break;
+        }
+    }
     Ok(())
 }
 
+// The length in bytes of the character at the start of `bytes`, or None
+// if it is cut off by the end of the slice.
+fn char_len(bytes: &[u8]) -> Option<usize> {
+    match std::str::from_utf8(&bytes[..bytes.len().min(4)]) {
+        Ok(s) => s.chars().next().map(char::len_utf8),
+        Err(e) if e.valid_up_to() > 0 => {
+            let s = std::str::from_utf8(&bytes[..e.valid_up_to()]).ok()?;
+            s.chars().next().map(char::len_utf8)
+        }
+        Err(e) => e.error_len(),
+    }
+}
+
+fn parse_count(val: &str) -> MyResult<Count> {
+    match val.strip_prefix('-') {
+        Some(num) => parse_size(num)
+            .map(Count::AllBut)
+            .map_err(|e| format!("-{}", e).into()),
+        None => parse_positive_int(val).map(Count::First),
+    }
+}
+
+// Parse a count with an optional GNU size suffix: b (512), kB (1000),
+// K or KiB (1024), MB, M or MiB, and so on up to Q.
+fn parse_size(val: &str) -> MyResult<usize> {
+    let too_large = || From::from(format!("{}: value too large", val));
+    let split = val.find(|c: char| !c.is_ascii_digit()).unwrap_or(val.len());
+    let (num, suffix) = val.split_at(split);
+    let num: usize = match num.parse() {
+        Ok(num) => num,
+        Err(e) if *e.kind() == IntErrorKind::PosOverflow => return Err(too_large()),
+        Err(_) => return Err(From::from(val)),
+    };
+    let multiplier = match suffix {
+        "" => Some(1),
+        "b" => Some(512),
+        _ => {
+            let mut chars = suffix.chars();
+            let power = match chars.next() {
+                Some('K' | 'k') => 1,
+                Some('M' | 'm') => 2,
+                Some('G') => 3,
+                Some('T') => 4,
+                Some('P') => 5,
+                Some('E') => 6,
+                Some('Z') => 7,
+                Some('Y') => 8,
+                Some('R') => 9,
+                Some('Q') => 10,
+                _ => return Err(From::from(val)),
+            };
+            let base: usize = match chars.as_str() {
+                "" | "iB" => 1024,
+                "B" => 1000,
+                _ => return Err(From::from(val)),
+            };
+            base.checked_pow(power)
+        }
+    };
+    multiplier
+        .and_then(|multiplier| num.checked_mul(multiplier))
+        .ok_or_else(too_large)
+}
+
 fn parse_positive_int(val: &str) -> MyResult<usize> {
-    match val.parse() {
-        Ok(n) if n > 0 => Ok(n),
+    match parse_size(val)? {
+        n if n > 0 => Ok(n),
         _ => Err(From::from(val)),
       }
 }
@@ -125,4 +329,90 @@ fn test_parse_positive_int() {
     // let res = parse_positive_int("0");
     // assert!(res.is_err());
     // assert_eq!(res.unwrap_err().to_string(), "0".to_string())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_parse_count() {
+    let res = parse_count("3");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Count::First(3));
+
+    // A leading dash counts from the end
+    let res = parse_count("-3");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Count::AllBut(3));
+
+    let res = parse_count("-0");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), Count::AllBut(0));
+
+    let res = parse_count("-foo");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "-foo".to_string());
+
+    let res = parse_count("--3");
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_parse_size() {
+    let res = parse_size("10");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 10);
+
+    let res = parse_size("2b");
+    assert!(res.is_ok());
+    assert_eq!(res.unwrap(), 1024);
+
+    // Decimal and binary units
+    assert_eq!(parse_size("1kB").unwrap(), 1000);
+    assert_eq!(parse_size("1K").unwrap(), 1024);
+    assert_eq!(parse_size("1k").unwrap(), 1024);
+    assert_eq!(parse_size("1KiB").unwrap(), 1024);
+    assert_eq!(parse_size("10MB").unwrap(), 10_000_000);
+    assert_eq!(parse_size("10M").unwrap(), 10 * 1024 * 1024);
+    assert_eq!(parse_size("3GiB").unwrap(), 3 << 30);
+    assert_eq!(parse_size("1GB").unwrap(), 1_000_000_000);
+
+    // Unknown suffixes and stray characters are errors
+    for bad in ["", "K", "10X", "10KB2", "10 K", "1.5M", "+10"] {
+        let res = parse_size(bad);
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), bad.to_string());
+    }
+
+    // Overflow is reported, not wrapped
+    let res = parse_size("10Z");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "10Z: value too large");
+
+    let res = parse_size("99999999999999999999");
+    assert!(res.is_err());
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "99999999999999999999: value too large"
+    );
+
+    let res = parse_count("-1Q");
+    assert!(res.is_err());
+    assert_eq!(res.unwrap_err().to_string(), "-1Q: value too large");
+
+    let res = parse_count("-2K");
+    assert_eq!(res.unwrap(), Count::AllBut(2048));
+}
+
+#[test]
+fn test_char_len() {
+    assert_eq!(char_len(b"abc"), Some(1));
+    assert_eq!(char_len("\u{d6}ne".as_bytes()), Some(2));
+    assert_eq!(char_len("\u{20ac}".as_bytes()), Some(3));
+    assert_eq!(char_len("\u{1f980}!".as_bytes()), Some(4));
+
+    // Cut off by the end of the buffer
+    assert_eq!(char_len(b"\xe2\x82"), None);
+    assert_eq!(char_len(b""), None);
+
+    // Invalid bytes count on their own
+    assert_eq!(char_len(b"\xffabc"), Some(1));
+    assert_eq!(char_len(b"\xe2\x82x"), Some(2));
+}
@@ -1,10 +1,13 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
-use std::io::prelude::*;
 use std::{
     error::Error,
-    fs::{self, File},
+    fs,
+    io::Write,
+    process::{self, Stdio},
+    thread,
+    time::Duration,
 };
 
 type TestResult = Result<(), Box<dyn Error>>;
@@ -15,6 +18,7 @@ const ONE: &str = "./tests/inputs/one.txt";
 const TWO: &str = "./tests/inputs/two.txt";
 const THREE: &str = "./tests/inputs/three.txt";
 const TEN: &str = "./tests/inputs/ten.txt";
+const NUL: &str = "./tests/inputs/nul.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -41,7 +45,21 @@ fn dies_bad_bytes() -> TestResult {
     let bad = random_string();
     let expected = format!("illegal byte count -- {}", &bad);
     Command::cargo_bin(PRG)?
-        .args(&["-c", &bad, EMPTY])
+        .args(["-c", &bad, EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_chars() -> TestResult {
+    let bad = random_string();
+    let expected = format!("illegal character count -- {}", &bad);
+    Command::cargo_bin(PRG)?
+        .args(["--chars", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -55,7 +73,7 @@ fn dies_bad_lines() -> TestResult {
     let bad = random_string();
     let expected = format!("illegal line count -- {}", &bad);
     Command::cargo_bin(PRG)?
-        .args(&["-n", &bad, EMPTY])
+        .args(["-n", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -70,7 +88,7 @@ fn dies_bytes_and_lines() -> TestResult {
                used with '--bytes <BYTES>'";
 
     Command::cargo_bin(PRG)?
-        .args(&["-n", "1", "-c", "2"])
+        .args(["-n", "1", "-c", "2"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(msg));
@@ -78,6 +96,28 @@ fn dies_bytes_and_lines() -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_count_too_large() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "10Z", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "illegal byte count -- 10Z: value too large",
+        ));
+
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-1Q", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "illegal line count -- -1Q: value too large",
+        ));
+
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn skips_bad_file() -> TestResult {
@@ -93,17 +133,14 @@ fn skips_bad_file() -> TestResult {
 
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> TestResult {
-    // Extra work here due to lossy UTF
-    let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
+    // Compare bytes, as -c may cut a multibyte character
+    let expected = fs::read(expected_file)?;
 
     Command::cargo_bin(PRG)?
         .args(args)
         .assert()
         .success()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(predicate::eq(expected.as_slice()));
 
     Ok(())
 }
@@ -114,18 +151,14 @@ fn run_stdin(
     input_file: &str,
     expected_file: &str,
 ) -> TestResult {
-    // Extra work here due to lossy UTF
-    let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
-    let input = fs::read_to_string(input_file)?;
+    let expected = fs::read(expected_file)?;
+    let input = fs::read(input_file)?;
 
     Command::cargo_bin(PRG)?
         .write_stdin(input)
         .args(args)
         .assert()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(predicate::eq(expected.as_slice()));
 
     Ok(())
 }
@@ -160,6 +193,36 @@ fn empty_c4() -> TestResult {
     run(&[EMPTY, "-c", "4"], "tests/expected/empty.txt.c4.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn empty_chars1() -> TestResult {
+    run(
+        &[EMPTY, "--chars", "1"],
+        "tests/expected/empty.txt.chars1.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn empty_chars4() -> TestResult {
+    run(
+        &[EMPTY, "--chars", "4"],
+        "tests/expected/empty.txt.chars4.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn empty_n_minus2() -> TestResult {
+    run(&[EMPTY, "-n", "-2"], "tests/expected/empty.txt.n-2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn empty_c_minus4() -> TestResult {
+    run(&[EMPTY, "-c", "-4"], "tests/expected/empty.txt.c-4.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn one() -> TestResult {
@@ -191,6 +254,16 @@ fn one_c4() -> TestResult {
     run(&[ONE, "-c", "4"], "tests/expected/one.txt.c4.out")
 }
 
+#[test]
+fn one_chars1() -> TestResult {
+    run(&[ONE, "--chars", "1"], "tests/expected/one.txt.chars1.out")
+}
+
+#[test]
+fn one_chars4() -> TestResult {
+    run(&[ONE, "--chars", "4"], "tests/expected/one.txt.chars4.out")
+}
+
 #[test]
 fn one_stdin() -> TestResult {
     run_stdin(&[], ONE, "tests/expected/one.txt.out")
@@ -221,6 +294,31 @@ fn one_c4_stdin() -> TestResult {
     run_stdin(&["-c", "4"], ONE, "tests/expected/one.txt.c4.out")
 }
 
+#[test]
+fn one_chars4_stdin() -> TestResult {
+    run_stdin(&["--chars", "4"], ONE, "tests/expected/one.txt.chars4.out")
+}
+
+#[test]
+fn one_n_minus2() -> TestResult {
+    run(&[ONE, "-n", "-2"], "tests/expected/one.txt.n-2.out")
+}
+
+#[test]
+fn one_c_minus4() -> TestResult {
+    run(&[ONE, "-c", "-4"], "tests/expected/one.txt.c-4.out")
+}
+
+#[test]
+fn one_n_minus2_stdin() -> TestResult {
+    run_stdin(&["-n", "-2"], ONE, "tests/expected/one.txt.n-2.out")
+}
+
+#[test]
+fn one_c_minus4_stdin() -> TestResult {
+    run_stdin(&["-c", "-4"], ONE, "tests/expected/one.txt.c-4.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn two() -> TestResult {
@@ -247,6 +345,16 @@ fn two_c4() -> TestResult {
     run(&[TWO, "-c", "4"], "tests/expected/two.txt.c4.out")
 }
 
+#[test]
+fn two_chars1() -> TestResult {
+    run(&[TWO, "--chars", "1"], "tests/expected/two.txt.chars1.out")
+}
+
+#[test]
+fn two_chars4() -> TestResult {
+    run(&[TWO, "--chars", "4"], "tests/expected/two.txt.chars4.out")
+}
+
 #[test]
 fn two_stdin() -> TestResult {
     run_stdin(&[], TWO, "tests/expected/two.txt.out")
@@ -272,6 +380,31 @@ fn two_c4_stdin() -> TestResult {
     run_stdin(&["-c", "4"], TWO, "tests/expected/two.txt.c4.out")
 }
 
+#[test]
+fn two_chars4_stdin() -> TestResult {
+    run_stdin(&["--chars", "4"], TWO, "tests/expected/two.txt.chars4.out")
+}
+
+#[test]
+fn two_n_minus2() -> TestResult {
+    run(&[TWO, "-n", "-2"], "tests/expected/two.txt.n-2.out")
+}
+
+#[test]
+fn two_c_minus4() -> TestResult {
+    run(&[TWO, "-c", "-4"], "tests/expected/two.txt.c-4.out")
+}
+
+#[test]
+fn two_n_minus2_stdin() -> TestResult {
+    run_stdin(&["-n", "-2"], TWO, "tests/expected/two.txt.n-2.out")
+}
+
+#[test]
+fn two_c_minus4_stdin() -> TestResult {
+    run_stdin(&["-c", "-4"], TWO, "tests/expected/two.txt.c-4.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn three() -> TestResult {
@@ -298,6 +431,22 @@ fn three_c4() -> TestResult {
     run(&[THREE, "-c", "4"], "tests/expected/three.txt.c4.out")
 }
 
+#[test]
+fn three_chars1() -> TestResult {
+    run(
+        &[THREE, "--chars", "1"],
+        "tests/expected/three.txt.chars1.out",
+    )
+}
+
+#[test]
+fn three_chars4() -> TestResult {
+    run(
+        &[THREE, "--chars", "4"],
+        "tests/expected/three.txt.chars4.out",
+    )
+}
+
 #[test]
 fn three_stdin() -> TestResult {
     run_stdin(&[], THREE, "tests/expected/three.txt.out")
@@ -323,6 +472,35 @@ fn three_c4_stdin() -> TestResult {
     run_stdin(&["-c", "4"], THREE, "tests/expected/three.txt.c4.out")
 }
 
+#[test]
+fn three_chars4_stdin() -> TestResult {
+    run_stdin(
+        &["--chars", "4"],
+        THREE,
+        "tests/expected/three.txt.chars4.out",
+    )
+}
+
+#[test]
+fn three_n_minus2() -> TestResult {
+    run(&[THREE, "-n", "-2"], "tests/expected/three.txt.n-2.out")
+}
+
+#[test]
+fn three_c_minus4() -> TestResult {
+    run(&[THREE, "-c", "-4"], "tests/expected/three.txt.c-4.out")
+}
+
+#[test]
+fn three_n_minus2_stdin() -> TestResult {
+    run_stdin(&["-n", "-2"], THREE, "tests/expected/three.txt.n-2.out")
+}
+
+#[test]
+fn three_c_minus4_stdin() -> TestResult {
+    run_stdin(&["-c", "-4"], THREE, "tests/expected/three.txt.c-4.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn ten() -> TestResult {
@@ -349,6 +527,26 @@ fn ten_c4() -> TestResult {
     run(&[TEN, "-c", "4"], "tests/expected/ten.txt.c4.out")
 }
 
+#[test]
+fn ten_chars1() -> TestResult {
+    run(&[TEN, "--chars", "1"], "tests/expected/ten.txt.chars1.out")
+}
+
+#[test]
+fn ten_chars4() -> TestResult {
+    run(&[TEN, "--chars", "4"], "tests/expected/ten.txt.chars4.out")
+}
+
+#[test]
+fn ten_n_suffix() -> TestResult {
+    run(&[TEN, "-n", "1K"], "tests/expected/ten.txt.out")
+}
+
+#[test]
+fn ten_c_suffix() -> TestResult {
+    run(&[TEN, "-c", "1b"], "tests/expected/ten.txt.out")
+}
+
 #[test]
 fn ten_stdin() -> TestResult {
     run_stdin(&[], TEN, "tests/expected/ten.txt.out")
@@ -374,6 +572,31 @@ fn ten_c4_stdin() -> TestResult {
     run_stdin(&["-c", "4"], TEN, "tests/expected/ten.txt.c4.out")
 }
 
+#[test]
+fn ten_chars4_stdin() -> TestResult {
+    run_stdin(&["--chars", "4"], TEN, "tests/expected/ten.txt.chars4.out")
+}
+
+#[test]
+fn ten_n_minus2() -> TestResult {
+    run(&[TEN, "-n", "-2"], "tests/expected/ten.txt.n-2.out")
+}
+
+#[test]
+fn ten_c_minus4() -> TestResult {
+    run(&[TEN, "-c", "-4"], "tests/expected/ten.txt.c-4.out")
+}
+
+#[test]
+fn ten_n_minus2_stdin() -> TestResult {
+    run_stdin(&["-n", "-2"], TEN, "tests/expected/ten.txt.n-2.out")
+}
+
+#[test]
+fn ten_c_minus4_stdin() -> TestResult {
+    run_stdin(&["-c", "-4"], TEN, "tests/expected/ten.txt.c-4.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn multiple_files() -> TestResult {
@@ -419,3 +642,123 @@ fn multiple_files_c4() -> TestResult {
         "tests/expected/all.c4.out",
     )
 }
+
+#[test]
+fn multiple_files_chars4() -> TestResult {
+    run(
+        &["--chars", "4", EMPTY, ONE, TWO, THREE, TEN],
+        "tests/expected/all.chars4.out",
+    )
+}
+
+#[test]
+fn multiple_files_quiet() -> TestResult {
+    run(
+        &["-q", "-n", "2", EMPTY, ONE, TWO, THREE, TEN],
+        "tests/expected/all.q.n2.out",
+    )
+}
+
+#[test]
+fn multiple_files_verbose_then_quiet() -> TestResult {
+    run(
+        &["-v", "-q", "-n", "2", EMPTY, ONE, TWO, THREE, TEN],
+        "tests/expected/all.q.n2.out",
+    )
+}
+
+#[test]
+fn one_verbose() -> TestResult {
+    run(&["-v", ONE], "tests/expected/one.txt.v.out")
+}
+
+#[test]
+fn one_quiet_then_verbose() -> TestResult {
+    run(
+        &["--quiet", "--verbose", ONE],
+        "tests/expected/one.txt.v.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn nul_n1() -> TestResult {
+    run(&[NUL, "-n", "1"], "tests/expected/nul.txt.n1.out")
+}
+
+#[test]
+fn nul_z_n2() -> TestResult {
+    run(&[NUL, "-z", "-n", "2"], "tests/expected/nul.txt.z.n2.out")
+}
+
+#[test]
+fn nul_z_n_minus2() -> TestResult {
+    run(&[NUL, "-z", "-n", "-2"], "tests/expected/nul.txt.z.n-2.out")
+}
+
+#[test]
+fn nul_z_n2_stdin() -> TestResult {
+    run_stdin(
+        &["--zero-terminated", "-n", "2"],
+        NUL,
+        "tests/expected/nul.txt.z.n2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_short_reads_from_pipe() -> TestResult {
+    // Feed stdin through a pipe in pieces with pauses between them, so a
+    // single read would only see the first piece
+    let mut child = process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(["-c", "14"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    for piece in ["one\n", "two\n", "three\n"] {
+        stdin.write_all(piece.as_bytes())?;
+        stdin.flush()?;
+        thread::sleep(Duration::from_millis(50));
+    }
+    drop(stdin);
+
+    let output = child.wait_with_output()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"one\ntwo\nthree\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_count_larger_than_memory() -> TestResult {
+    run(&[TEN, "-c", "50G"], "tests/expected/ten.txt.out")
+}
+
+#[test]
+fn multiple_files_n_minus2() -> TestResult {
+    run(
+        &["-n", "-2", EMPTY, ONE, TWO, THREE, TEN],
+        "tests/expected/all.n-2.out",
+    )
+}
+
+#[test]
+fn multiple_files_c_minus4() -> TestResult {
+    run(
+        &[EMPTY, ONE, TWO, THREE, TEN, "-c", "-4"],
+        "tests/expected/all.c-4.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn n_minus_more_than_input() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-20", TEN])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}